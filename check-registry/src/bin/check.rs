@@ -0,0 +1,119 @@
+//! Lint the token tables of every proposal before a release: reject tokens
+//! listed twice in a table, zero precisions, tokens that differ from the token
+//! registry shared by the proposals, and, given a checkout of the
+//! Cosmos chain registry, channels that are not live and denominations that
+//! differ from the exponent of the base denomination sent over the channel.
+
+use std::collections::BTreeSet;
+use std::process;
+
+use check_registry::config::CheckConfig;
+use check_registry::registry::{base_denoms, live_channels};
+use check_registry::tables::{ibc_tokens, registry_tokens};
+use clap::Parser;
+use pre_phase4_core::TokenAddress;
+// Link the native host functions imported by the transactions
+use test_utils as _;
+
+fn main() {
+    let config = CheckConfig::parse();
+
+    let mut failures = Vec::new();
+
+    // Every table lists a token at most once
+    let mut listed = BTreeSet::new();
+    for token in ibc_tokens() {
+        if !listed.insert((token.phase, token.channel_id, token.base_token)) {
+            failures.push(format!(
+                "{}: {}/{} is listed twice",
+                token.phase, token.channel_id, token.base_token
+            ));
+        }
+    }
+    let mut reset_tokens = BTreeSet::new();
+    for (token_address, _, precision) in &pre_phase4_core::TOKENS {
+        let token_address = token_address.to_address();
+        if *precision == 0 {
+            failures.push(format!("pre-phase4: {token_address} has a zero precision"));
+        }
+        if !reset_tokens.insert(token_address.clone()) {
+            failures.push(format!("pre-phase4: {token_address} is listed twice"));
+        }
+    }
+
+    // Every IBC token of the tables is in the token registry with the same
+    // denomination, and the registry holds the addresses of its tokens
+    let registry = registry_tokens();
+    for token in ibc_tokens() {
+        let Some(registered) = registry.iter().find(|registered| {
+            registered.channel == token.channel_id && registered.base == token.base_token
+        }) else {
+            failures.push(format!(
+                "{}: {}/{} is not in the token registry",
+                token.phase, token.channel_id, token.base_token
+            ));
+            continue;
+        };
+        if let Some(denomination) = token.denomination {
+            if denomination != registered.denomination {
+                failures.push(format!(
+                    "{}: {}/{} has the denomination {denomination} but the token registry \
+                     has {}",
+                    token.phase, token.channel_id, token.base_token, registered.denomination
+                ));
+            }
+        }
+    }
+    for registered in &registry {
+        let address = TokenAddress::Ibc(registered.channel, registered.base).to_address();
+        if address.to_string() != registered.address {
+            failures.push(format!(
+                "token registry: {} has the address {} but {}/{} hashes to {address}",
+                registered.symbol, registered.address, registered.channel, registered.base
+            ));
+        }
+    }
+
+    match &config.registry {
+        Some(registry) => {
+            let channels = live_channels(registry, &config.chain_name);
+            for token in ibc_tokens() {
+                let Some(counterparty) = channels.get(token.channel_id) else {
+                    failures.push(format!(
+                        "{}: {} is not a live channel of {}",
+                        token.phase, token.channel_id, config.chain_name
+                    ));
+                    continue;
+                };
+                let Some(exponent) = base_denoms(registry, counterparty)
+                    .get(token.base_token)
+                    .copied()
+                else {
+                    failures.push(format!(
+                        "{}: {} is not an asset of {counterparty}",
+                        token.phase, token.base_token
+                    ));
+                    continue;
+                };
+                if let Some(denomination) = token.denomination {
+                    if denomination != exponent {
+                        failures.push(format!(
+                            "{}: {}/{} has the denomination {denomination} but its base \
+                             denomination has the exponent {exponent} on {counterparty}",
+                            token.phase, token.channel_id, token.base_token
+                        ));
+                    }
+                }
+            }
+        }
+        None => println!("No chain registry given, skipping the checks against it"),
+    }
+
+    for failure in &failures {
+        println!("FAIL {failure}");
+    }
+    if !failures.is_empty() {
+        process::exit(1)
+    }
+    println!("The token tables are consistent");
+}
@@ -0,0 +1,81 @@
+//! Query the counterparty chain of every IBC token of the token tables and
+//! check that the channel at its end is open, leads back to the channel of
+//! the table, and escrows some of the base token. This catches the classic
+//! mistake of a token listed with the channel number of another chain, which
+//! the registry alone cannot catch when that channel also carries the token.
+
+use std::process;
+
+use check_registry::config::AppConfig;
+use check_registry::counterparty::{channel_state, escrowed_amount};
+use check_registry::registry::{live_counterparties, rest_endpoints};
+use check_registry::tables::ibc_tokens;
+use clap::Parser;
+// Link the native host functions imported by the transactions
+use test_utils as _;
+
+fn main() {
+    let config = AppConfig::parse();
+
+    let counterparties = live_counterparties(&config.registry, &config.chain_name);
+    let mut failures = 0;
+    for token in ibc_tokens() {
+        let (phase, channel_id, base_token) = (token.phase, token.channel_id, token.base_token);
+        let Some(counterparty) = counterparties.get(channel_id) else {
+            println!(
+                "FAIL {phase}: {channel_id} is not a live channel of {}",
+                config.chain_name
+            );
+            failures += 1;
+            continue;
+        };
+        let Some(rest) = rest_endpoints(&config.registry, &counterparty.chain_name)
+            .into_iter()
+            .next()
+        else {
+            println!(
+                "FAIL {phase}: {} has no REST endpoint in the registry",
+                counterparty.chain_name
+            );
+            failures += 1;
+            continue;
+        };
+
+        let check = || -> Result<(), String> {
+            let state = channel_state(&rest, &counterparty.channel_id)?;
+            if !state.open {
+                return Err(format!(
+                    "{} is not open on {}",
+                    counterparty.channel_id, counterparty.chain_name
+                ));
+            }
+            if state.counterparty_channel_id != channel_id {
+                return Err(format!(
+                    "{} on {} leads to {} rather than {channel_id}",
+                    counterparty.channel_id, counterparty.chain_name, state.counterparty_channel_id
+                ));
+            }
+            if escrowed_amount(&rest, &counterparty.channel_id, base_token)? == 0 {
+                return Err(format!(
+                    "no {base_token} flows from {} over {}",
+                    counterparty.chain_name, counterparty.channel_id
+                ));
+            }
+            Ok(())
+        };
+        match check() {
+            Ok(()) => println!(
+                "PASS {phase}: {channel_id}/{base_token} flows from {} over {}",
+                counterparty.chain_name, counterparty.channel_id
+            ),
+            Err(error) => {
+                println!("FAIL {phase}: {channel_id}/{base_token}: {error}");
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        process::exit(1)
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    /// A checkout of the Cosmos chain registry
+    #[clap(long, env)]
+    pub registry: PathBuf,
+
+    /// The name of Namada in the chain registry
+    #[clap(long, env, default_value = "namada")]
+    pub chain_name: String,
+}
+
+#[derive(clap::Parser)]
+pub struct CheckConfig {
+    /// A checkout of the Cosmos chain registry. Without it, only the checks
+    /// of the tables against each other are run.
+    #[clap(long, env)]
+    pub registry: Option<PathBuf>,
+
+    /// The name of Namada in the chain registry
+    #[clap(long, env, default_value = "namada")]
+    pub chain_name: String,
+}
@@ -0,0 +1,59 @@
+//! Queries of the REST endpoints of the counterparty chains, checking that the
+//! channels of the token tables lead to Namada and that the base tokens flow
+//! over them.
+
+use serde_json::Value;
+
+/// The state of a channel on the counterparty chain
+pub struct ChannelState {
+    /// Whether the channel is open
+    pub open: bool,
+    /// The ID of the channel at the other end, i.e. on Namada
+    pub counterparty_channel_id: String,
+}
+
+/// Query the given REST endpoint for the JSON at the given path
+fn query(rest: &str, path: &str) -> Result<Value, String> {
+    ureq::get(&format!("{rest}{path}"))
+        .call()
+        .map_err(|error| format!("unable to query {rest}{path}: {error}"))?
+        .into_json()
+        .map_err(|error| format!("unable to decode {rest}{path}: {error}"))
+}
+
+/// Query the state of the given transfer channel of the counterparty chain
+pub fn channel_state(rest: &str, channel_id: &str) -> Result<ChannelState, String> {
+    let response = query(
+        rest,
+        &format!("/ibc/core/channel/v1/channels/{channel_id}/ports/transfer"),
+    )?;
+    let channel = &response["channel"];
+    Ok(ChannelState {
+        open: channel["state"] == "STATE_OPEN",
+        counterparty_channel_id: channel["counterparty"]["channel_id"]
+            .as_str()
+            .ok_or_else(|| format!("{channel_id} has no counterparty channel"))?
+            .to_owned(),
+    })
+}
+
+/// Query the amount of the given denomination escrowed on the counterparty
+/// chain for the transfers over the given channel, i.e. the amount that has
+/// flowed to the other end of the channel and not come back
+pub fn escrowed_amount(rest: &str, channel_id: &str, denom: &str) -> Result<u128, String> {
+    let response = query(
+        rest,
+        &format!("/ibc/apps/transfer/v1/channels/{channel_id}/ports/transfer/escrow_address"),
+    )?;
+    let escrow_address = response["escrow_address"]
+        .as_str()
+        .ok_or_else(|| format!("{channel_id} has no escrow address"))?;
+    let response = query(
+        rest,
+        &format!("/cosmos/bank/v1beta1/balances/{escrow_address}/by_denom?denom={denom}"),
+    )?;
+    response["balance"]["amount"]
+        .as_str()
+        .and_then(|amount| amount.parse().ok())
+        .ok_or_else(|| format!("the escrow of {channel_id} has no balance of {denom}"))
+}
@@ -0,0 +1,8 @@
+//! Checks of the token tables of the proposals, against each other, against a
+//! checkout of the Cosmos chain registry, and against the counterparty chains
+//! themselves.
+
+pub mod config;
+pub mod counterparty;
+pub mod registry;
+pub mod tables;
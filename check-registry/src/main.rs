@@ -0,0 +1,42 @@
+//! Check every IBC token of the token tables against a checkout of the Cosmos
+//! chain registry: its channel must be a live Namada channel, and its base
+//! token must be an asset of the chain at the other end of that channel. This
+//! catches a token copied with the channel of another token, which would
+//! otherwise configure an address that no transfer ever mints.
+
+use std::process;
+
+use check_registry::config::AppConfig;
+use check_registry::registry::{base_denoms, live_channels};
+use check_registry::tables::ibc_tokens;
+use clap::Parser;
+// Link the native host functions imported by the transactions
+use test_utils as _;
+
+fn main() {
+    let config = AppConfig::parse();
+
+    let channels = live_channels(&config.registry, &config.chain_name);
+    let mut failures = 0;
+    for token in ibc_tokens() {
+        let (phase, channel_id, base_token) = (token.phase, token.channel_id, token.base_token);
+        let Some(counterparty) = channels.get(channel_id) else {
+            println!(
+                "FAIL {phase}: {channel_id} is not a live channel of {}",
+                config.chain_name
+            );
+            failures += 1;
+            continue;
+        };
+        if base_denoms(&config.registry, counterparty).contains_key(base_token) {
+            println!("PASS {phase}: {channel_id}/{base_token} comes from {counterparty}");
+        } else {
+            println!("FAIL {phase}: {base_token} is not an asset of {counterparty}, the chain at the other end of {channel_id}");
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        process::exit(1)
+    }
+}
@@ -0,0 +1,168 @@
+//! The parts of the Cosmos chain registry describing the channels of a chain
+//! and the assets of its counterparties.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// A file of the `_IBC` directory, describing the channels between two chains
+#[derive(Deserialize)]
+struct IbcConnection {
+    chain_1: ChainRef,
+    chain_2: ChainRef,
+    channels: Vec<IbcChannel>,
+}
+
+#[derive(Deserialize)]
+struct ChainRef {
+    chain_name: String,
+}
+
+#[derive(Deserialize)]
+struct IbcChannel {
+    chain_1: ChannelEnd,
+    chain_2: ChannelEnd,
+    #[serde(default)]
+    tags: ChannelTags,
+}
+
+#[derive(Deserialize)]
+struct ChannelEnd {
+    channel_id: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ChannelTags {
+    status: Option<String>,
+}
+
+/// The `chain.json` file of a chain
+#[derive(Deserialize)]
+struct Chain {
+    #[serde(default)]
+    apis: Apis,
+}
+
+#[derive(Deserialize, Default)]
+struct Apis {
+    #[serde(default)]
+    rest: Vec<Endpoint>,
+}
+
+#[derive(Deserialize)]
+struct Endpoint {
+    address: String,
+}
+
+/// The `assetlist.json` file of a chain
+#[derive(Deserialize)]
+struct AssetList {
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    base: String,
+    #[serde(default)]
+    denom_units: Vec<DenomUnit>,
+}
+
+#[derive(Deserialize)]
+struct DenomUnit {
+    denom: String,
+    exponent: u8,
+}
+
+/// The end of a channel on the counterparty chain
+pub struct Counterparty {
+    /// The name of the counterparty chain in the registry
+    pub chain_name: String,
+    /// The ID of the channel on the counterparty chain
+    pub channel_id: String,
+}
+
+/// Get the counterparty chain of every live channel of the given chain
+pub fn live_channels(registry: &Path, chain_name: &str) -> BTreeMap<String, String> {
+    live_counterparties(registry, chain_name)
+        .into_iter()
+        .map(|(channel_id, counterparty)| (channel_id, counterparty.chain_name))
+        .collect()
+}
+
+/// Get the counterparty end of every live channel of the given chain, keyed by
+/// the ID of the channel on the given chain
+pub fn live_counterparties(registry: &Path, chain_name: &str) -> BTreeMap<String, Counterparty> {
+    let mut channels = BTreeMap::new();
+    for entry in fs::read_dir(registry.join("_IBC")).unwrap() {
+        let path = entry.unwrap().path();
+        let connection: IbcConnection = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        for channel in connection.channels {
+            if channel
+                .tags
+                .status
+                .as_deref()
+                .is_some_and(|status| status != "live")
+            {
+                continue;
+            }
+            if connection.chain_1.chain_name == chain_name {
+                channels.insert(
+                    channel.chain_1.channel_id,
+                    Counterparty {
+                        chain_name: connection.chain_2.chain_name.clone(),
+                        channel_id: channel.chain_2.channel_id,
+                    },
+                );
+            } else if connection.chain_2.chain_name == chain_name {
+                channels.insert(
+                    channel.chain_2.channel_id,
+                    Counterparty {
+                        chain_name: connection.chain_1.chain_name.clone(),
+                        channel_id: channel.chain_1.channel_id,
+                    },
+                );
+            }
+        }
+    }
+    channels
+}
+
+/// Get the addresses of the REST endpoints of the given chain
+pub fn rest_endpoints(registry: &Path, chain_name: &str) -> Vec<String> {
+    let path = registry.join(chain_name).join("chain.json");
+    let Ok(chain) = fs::read(&path) else {
+        return Vec::new();
+    };
+    let chain: Chain = serde_json::from_slice(&chain).unwrap();
+    chain
+        .apis
+        .rest
+        .into_iter()
+        .map(|endpoint| endpoint.address.trim_end_matches('/').to_owned())
+        .collect()
+}
+
+/// Get the exponent of the base denomination of every asset of the given
+/// chain, keyed by that denomination. The base denomination is the one sent
+/// over IBC, so its exponent is the denomination of the IBC token on Namada.
+pub fn base_denoms(registry: &Path, chain_name: &str) -> BTreeMap<String, u8> {
+    let path = registry.join(chain_name).join("assetlist.json");
+    let Ok(asset_list) = fs::read(&path) else {
+        return BTreeMap::new();
+    };
+    let asset_list: AssetList = serde_json::from_slice(&asset_list).unwrap();
+    asset_list
+        .assets
+        .into_iter()
+        .map(|asset| {
+            let exponent = asset
+                .denom_units
+                .iter()
+                .find(|unit| unit.denom == asset.base)
+                .map_or(0, |unit| unit.exponent);
+            (asset.base, exponent)
+        })
+        .collect()
+}
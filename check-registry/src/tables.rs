@@ -0,0 +1,69 @@
+//! The IBC tokens listed in the token tables of every proposal.
+
+use pre_phase4_core::TokenAddress;
+use serde::Deserialize;
+
+/// An IBC token listed in the table of a proposal
+pub struct IbcToken {
+    /// The proposal listing the token
+    pub phase: &'static str,
+    pub channel_id: &'static str,
+    pub base_token: &'static str,
+    /// The denomination of the token, if the table sets one
+    pub denomination: Option<u8>,
+    /// The reward precision of the token, if the table sets one
+    pub precision: Option<u128>,
+}
+
+/// Get every IBC token of the tables of the proposals, in the order in which
+/// the proposals were activated
+pub fn ibc_tokens() -> Vec<IbcToken> {
+    let mut tokens = Vec::new();
+    for (channel_id, base_token, _, _, _) in cross_phase::phase3::IBC_TOKENS {
+        tokens.push(IbcToken {
+            phase: "phase3",
+            channel_id,
+            base_token,
+            denomination: None,
+            precision: None,
+        });
+    }
+    for (denomination, channel_id, base_token, _, _, _, _) in cross_phase::phase4::IBC_TOKENS {
+        tokens.push(IbcToken {
+            phase: "phase4",
+            channel_id,
+            base_token,
+            denomination: Some(denomination),
+            precision: None,
+        });
+    }
+    for (token_address, denomination, precision) in &pre_phase4_core::TOKENS {
+        if let TokenAddress::Ibc(channel_id, base_token) = token_address {
+            tokens.push(IbcToken {
+                phase: "pre-phase4",
+                channel_id,
+                base_token,
+                denomination: Some(denomination.0),
+                precision: Some(*precision),
+            });
+        }
+    }
+    tokens
+}
+
+/// A token of the token registry shared by the proposals
+#[derive(Deserialize)]
+pub struct RegistryToken {
+    pub symbol: &'static str,
+    pub channel: &'static str,
+    pub base: &'static str,
+    pub denomination: u8,
+    /// The Namada address of the token
+    pub address: &'static str,
+}
+
+/// Get every token of the token registry shared by the proposals
+pub fn registry_tokens() -> Vec<RegistryToken> {
+    serde_json::from_str(include_str!("../../token-registry.json"))
+        .expect("unable to parse the token registry")
+}
@@ -0,0 +1,46 @@
+//! Compare the writes of two proposals, each applied on its own to the state
+//! of phase 1, and print the keys that both of them write. The keys written
+//! with different values are marked and listed with both values, as they are
+//! the ones a proposal superseding part of another one overrides.
+
+use clap::Parser;
+use cross_phase::config::DiffConfig;
+use cross_phase::planned_writes;
+// Link the native host functions imported by the transactions
+use test_utils as _;
+
+fn main() {
+    let config = DiffConfig::parse();
+
+    let first = planned_writes(config.first).unwrap();
+    let second = planned_writes(config.second).unwrap();
+    let render = |value: &Option<Vec<u8>>| {
+        value
+            .as_ref()
+            .map(hex::encode)
+            .unwrap_or_else(|| "(deleted)".to_owned())
+    };
+
+    let mut shared = 0;
+    let mut differing = 0;
+    for (key, first_value) in &first {
+        let Some(second_value) = second.get(key) else {
+            continue;
+        };
+        shared += 1;
+        if first_value == second_value {
+            if config.all {
+                println!("  {key}");
+            }
+            continue;
+        }
+        differing += 1;
+        println!("! {key}");
+        println!("    {:?}: {}", config.first, render(first_value));
+        println!("    {:?}: {}", config.second, render(second_value));
+    }
+    println!(
+        "{shared} keys written by both {:?} and {:?}, {differing} of them with different values",
+        config.first, config.second
+    );
+}
@@ -0,0 +1,16 @@
+use crate::Phase;
+
+#[derive(clap::Parser)]
+pub struct DiffConfig {
+    /// The first proposal to compare
+    #[clap(value_enum)]
+    pub first: Phase,
+
+    /// The second proposal to compare
+    #[clap(value_enum)]
+    pub second: Phase,
+
+    /// Also list the keys that both proposals write with the same value
+    #[clap(long, env)]
+    pub all: bool,
+}
@@ -0,0 +1,44 @@
+//! A copy of the `increase_target_staked_ratio` proposal, applied to any storage.
+
+use dec::Dec;
+use namada_tx_prelude::*;
+
+use std::str::FromStr;
+
+use namada_proof_of_stake::storage::{read_pos_params, write_pos_params};
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {
+    let mut pos_params = read_pos_params::<S, governance::Store<S>>(ctx)?.owned;
+    pos_params.target_staked_ratio = Dec::from_str("0.55").unwrap();
+    write_pos_params(ctx, &pos_params)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use governance::parameters::GovernanceParameters;
+    use namada_proof_of_stake::parameters::OwnedPosParams;
+    use test_utils::{assert_snapshot, MockCtx};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = MockCtx::default();
+        GovernanceParameters::default()
+            .init_storage(&mut ctx)
+            .unwrap();
+        write_pos_params(&mut ctx, &OwnedPosParams::default()).unwrap();
+        ctx.commit();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/snapshots/increase_target_staked_ratio.snap"
+            ),
+        );
+    }
+}
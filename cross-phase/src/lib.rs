@@ -0,0 +1,303 @@
+//! Consistency checks across the governance transactions of every phase.
+//!
+//! Each phase is a separate proposal with its own token table, written at a
+//! different time. Nothing but these tests ensures that the tables agree on
+//! the tokens they share, so that a later proposal does not silently configure
+//! a different token than the one an earlier proposal set up.
+//!
+//! [`replay`] applies the proposals one after the other to the same storage,
+//! so that the interactions between proposals can be tested on the state they
+//! leave behind together rather than on the state each leaves on its own.
+//! [`planned_writes`] instead applies a single proposal, so that the writes of
+//! two proposals can be compared.
+//!
+//! The proposals already executed on mainnet are copied into the modules of
+//! this crate rather than linked, so that their crates, and with them the
+//! WASM that was voted on, stay exactly as they were executed.
+
+pub mod config;
+pub mod increase_target_staked_ratio;
+pub mod phase2;
+pub mod phase3;
+pub mod phase4;
+pub mod phase5a;
+pub mod phase5b;
+pub mod update_ibc_rate_limits;
+pub mod update_wasm;
+
+use std::collections::BTreeMap;
+
+use governance::parameters::GovernanceParameters;
+use namada_proof_of_stake::parameters::OwnedPosParams;
+use namada_proof_of_stake::storage::write_pos_params;
+use namada_tx_prelude::*;
+use pre_phase4_core::ResetSummary;
+use test_utils::MockCtx;
+
+/// A proposal whose writes can be planned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Phase {
+    Phase2,
+    Phase3,
+    Phase4,
+    PrePhase4,
+    Phase5a,
+    Phase5b,
+    UpdateIbcRateLimits,
+    IncreaseTargetStakedRatio,
+}
+
+impl Phase {
+    /// Apply the proposal to the given storage
+    pub fn apply<S: StorageRead + StorageWrite>(self, ctx: &mut S) -> TxResult {
+        match self {
+            Self::Phase2 => phase2::apply(ctx),
+            Self::Phase3 => phase3::apply(ctx),
+            Self::Phase4 => phase4::apply(ctx),
+            Self::PrePhase4 => pre_phase4_core::apply(ctx).map(|_| ()),
+            Self::Phase5a => phase5a::apply(ctx),
+            Self::Phase5b => phase5b::apply(ctx),
+            Self::UpdateIbcRateLimits => update_ibc_rate_limits::apply(ctx),
+            Self::IncreaseTargetStakedRatio => increase_target_staked_ratio::apply(ctx),
+        }
+    }
+}
+
+/// Get a storage holding the parts of the state of phase 1 that the proposals
+/// read: the governance and PoS parameters, and the MASP balances of the IBC
+/// tokens, which become their initial locked amounts
+pub fn phase1_state() -> MockCtx {
+    let mut ctx = MockCtx::default();
+    GovernanceParameters::default()
+        .init_storage(&mut ctx)
+        .expect("unable to initialize the governance parameters");
+    write_pos_params(&mut ctx, &OwnedPosParams::default())
+        .expect("unable to initialize the PoS parameters");
+    for (_, channel_id, base_token, _, _, _, _) in phase4::IBC_TOKENS {
+        let token_address = ibc::ibc_token(format!("transfer/{channel_id}/{base_token}"));
+        let ibc_balance_key = token::storage_key::balance_key(
+            &token_address,
+            &Address::Internal(address::InternalAddress::Masp),
+        );
+        ctx.init(&ibc_balance_key, token::Amount::from_u64(1_000_000));
+    }
+    ctx.commit();
+    ctx
+}
+
+/// Apply the given proposal on its own to the state of phase 1 and get the
+/// values it writes, or `None` for the keys it deletes
+pub fn planned_writes(phase: Phase) -> Result<BTreeMap<storage::Key, Option<Vec<u8>>>> {
+    let mut ctx = phase1_state();
+    phase.apply(&mut ctx)?;
+    Ok(ctx.diff())
+}
+
+/// Apply every proposal, in the order in which they were activated on mainnet,
+/// to the given storage holding the state of phase 1. Return the summaries of
+/// the conversions reset by the pre-phase4 proposal.
+pub fn replay<S: StorageRead + StorageWrite>(ctx: &mut S) -> Result<Vec<ResetSummary>> {
+    phase2::apply(ctx)?;
+    phase3::apply(ctx)?;
+    phase4::apply(ctx)?;
+    pre_phase4_core::apply(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use clap::ValueEnum;
+    use dec::Dec;
+    use masp::{MaspEpoch, Precision};
+    use namada_proof_of_stake::storage::read_pos_params;
+    use pre_phase4_core::TokenAddress;
+    use test_utils::{assert_golden, assert_snapshot, render_state_export};
+
+    use super::*;
+
+    /// Get the channel and base token of every IBC token of every phase,
+    /// along with the phase that lists it
+    fn ibc_tokens() -> Vec<(&'static str, &'static str, &'static str)> {
+        let mut tokens = Vec::new();
+        for (channel_id, base_token, _, _, _) in phase3::IBC_TOKENS {
+            tokens.push(("phase3", channel_id, base_token));
+        }
+        for (_, channel_id, base_token, _, _, _, _) in phase4::IBC_TOKENS {
+            tokens.push(("phase4", channel_id, base_token));
+        }
+        for (token_address, _, _) in &pre_phase4_core::TOKENS {
+            if let TokenAddress::Ibc(channel_id, base_token) = token_address {
+                tokens.push(("pre-phase4", *channel_id, *base_token));
+            }
+        }
+        for (channel_id, base_token, _, _) in update_ibc_rate_limits::IBC_TOKENS {
+            tokens.push(("update_ibc_rate_limits", channel_id, base_token));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_base_tokens_come_through_a_single_channel() {
+        let mut channels: BTreeMap<&str, (&str, &str)> = BTreeMap::new();
+        for (phase, channel_id, base_token) in ibc_tokens() {
+            let (first_phase, first_channel_id) =
+                *channels.entry(base_token).or_insert((phase, channel_id));
+            assert_eq!(
+                channel_id, first_channel_id,
+                "{base_token} comes through {first_channel_id} in {first_phase} but \
+                 through {channel_id} in {phase}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ibc_tokens_resolve_to_the_same_address() {
+        for (phase, channel_id, base_token) in ibc_tokens() {
+            let ibc_denom = format!("transfer/{channel_id}/{base_token}");
+            assert_eq!(
+                TokenAddress::Ibc(channel_id, base_token).to_address(),
+                ibc::ibc_token(&ibc_denom),
+                "the address of {ibc_denom} in {phase} differs from the pre-phase4 one"
+            );
+        }
+    }
+
+    #[test]
+    fn test_denominations_agree() {
+        let reset_denominations: BTreeMap<Address, u8> = pre_phase4_core::TOKENS
+            .iter()
+            .map(|(token_address, denomination, _)| (token_address.to_address(), denomination.0))
+            .collect();
+        for (denomination, channel_id, base_token, _, _, _, _) in phase4::IBC_TOKENS {
+            let token_address = TokenAddress::Ibc(channel_id, base_token).to_address();
+            if let Some(reset_denomination) = reset_denominations.get(&token_address) {
+                assert_eq!(
+                    denomination, *reset_denomination,
+                    "{channel_id}/{base_token} has conflicting denominations"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_tokens_are_listed_once() {
+        let mut precisions = BTreeMap::new();
+        for (token_address, _, precision) in &pre_phase4_core::TOKENS {
+            let token_address = token_address.to_address();
+            if let Some(other_precision) = precisions.insert(token_address.clone(), *precision) {
+                panic!(
+                    "{token_address} is listed with the precisions {other_precision} and \
+                     {precision}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rewarded_tokens_are_reset() {
+        let reset_tokens: Vec<Address> = pre_phase4_core::TOKENS
+            .iter()
+            .map(|(token_address, _, _)| token_address.to_address())
+            .collect();
+        for (_, channel_id, base_token, _, _, _, _) in phase4::IBC_TOKENS {
+            let token_address = TokenAddress::Ibc(channel_id, base_token).to_address();
+            assert!(
+                reset_tokens.contains(&token_address),
+                "{channel_id}/{base_token} earns rewards from phase4 but is not reset"
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay_all_phases() {
+        let mut ctx = phase1_state();
+        let summaries = replay(&mut ctx).unwrap();
+
+        // The inflation turned on by phase2 survives the later proposals
+        let pos_params = read_pos_params::<_, governance::Store<_>>(&ctx)
+            .unwrap()
+            .owned;
+        assert_eq!(
+            pos_params.max_inflation_rate,
+            Dec::from_str("0.05").unwrap()
+        );
+
+        // The tokens added to the MASP by phase3 remain there
+        let token_map: masp::TokenMap = ctx
+            .read(&token::storage_key::masp_token_map_key())
+            .unwrap()
+            .unwrap();
+        for (channel_id, base_token, _, _, _) in phase3::IBC_TOKENS {
+            assert!(token_map.contains_key(&format!("transfer/{channel_id}/{base_token}")));
+        }
+
+        // The rewards enabled by phase4 replace the null rewards of phase3 and
+        // are left alone by the reset of their conversions
+        for (_, channel_id, base_token, max_reward, _, _, _) in phase4::IBC_TOKENS {
+            let token_address = ibc::ibc_token(format!("transfer/{channel_id}/{base_token}"));
+            assert_eq!(
+                ctx.read::<Dec>(&token::storage_key::masp_max_reward_rate_key(
+                    &token_address
+                ))
+                .unwrap(),
+                Some(Dec::from_str(max_reward).unwrap())
+            );
+        }
+
+        // Every reset token has its precision scheduled
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for (token_address, _, precision) in &pre_phase4_core::TOKENS {
+            let scheduled_precision_key = token::storage_key::masp_scheduled_reward_precision_key(
+                &target,
+                &token_address.to_address(),
+            );
+            assert_eq!(
+                ctx.read::<Precision>(&scheduled_precision_key).unwrap(),
+                Some(*precision)
+            );
+        }
+
+        // The conversions are covered by the digest stored in the audit key
+        // rather than listed one by one
+        let mut diff = ctx.diff();
+        diff.retain(|key, _| {
+            !summaries
+                .iter()
+                .any(|summary| summary.conversions.contains_key(key))
+        });
+        assert_snapshot(
+            &diff,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/replay.snap"),
+        );
+    }
+
+    /// Check that the mainnet fixture of pre-phase4 holds the state that the
+    /// proposals activated before it leave behind on top of phase 1, so that
+    /// the regression tests of pre-phase4 run over that state without
+    /// depending on this crate
+    #[test]
+    fn test_pre_phase4_fixture_is_up_to_date() {
+        let mut ctx = phase1_state();
+        phase2::apply(&mut ctx).unwrap();
+        phase3::apply(&mut ctx).unwrap();
+        phase4::apply(&mut ctx).unwrap();
+        assert_golden(
+            &render_state_export(ctx.storage()),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../pre-phase4-core/fixtures/mainnet.state"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_every_phase_plans_writes_on_phase1_state() {
+        for phase in Phase::value_variants() {
+            let writes = planned_writes(*phase).unwrap_or_else(|error| {
+                panic!("{phase:?} failed on the state of phase 1: {error}")
+            });
+            assert!(!writes.is_empty(), "{phase:?} writes nothing");
+        }
+    }
+}
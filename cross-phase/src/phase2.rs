@@ -0,0 +1,50 @@
+//! A copy of the `phase2` proposal, applied to any storage.
+
+use dec::Dec;
+use namada_tx_prelude::*;
+
+use std::str::FromStr;
+
+use namada_proof_of_stake::storage::{read_pos_params, write_pos_params};
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {
+    // Turn on PoS inflation
+    let mut pos_params = read_pos_params::<S, governance::Store<S>>(ctx)?.owned;
+    pos_params.max_inflation_rate = Dec::from_str("0.05").unwrap();
+    pos_params.target_staked_ratio = Dec::from_str("0.4").unwrap();
+    pos_params.rewards_gain_p = Dec::from_str("0.5").unwrap();
+    pos_params.rewards_gain_d = Dec::from_str("0.5").unwrap();
+    write_pos_params(ctx, &pos_params)?;
+
+    // Turn on PGF inflation
+    let pgf_inflation_key = governance::pgf::storage::keys::get_pgf_inflation_rate_key();
+    let pgf_inflation_rate = Dec::from_str("0.05").unwrap();
+    ctx.write(&pgf_inflation_key, pgf_inflation_rate)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use governance::parameters::GovernanceParameters;
+    use namada_proof_of_stake::parameters::OwnedPosParams;
+    use test_utils::{assert_snapshot, MockCtx};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = MockCtx::default();
+        GovernanceParameters::default()
+            .init_storage(&mut ctx)
+            .unwrap();
+        write_pos_params(&mut ctx, &OwnedPosParams::default()).unwrap();
+        ctx.commit();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/phase2.snap"),
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! A copy of the `phase5a` proposal, applied to any storage.
+
+use namada_tx_prelude::*;
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {
+    // 1. Enable NAM transfers
+    let native_token_transferable_key = parameters_storage::get_native_token_transferable_key();
+    ctx.write(&native_token_transferable_key, true)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{assert_snapshot, MockCtx};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = MockCtx::default();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/phase5a.snap"),
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! A copy of the `phase5b` proposal, applied to any storage.
+
+use namada_tx_prelude::*;
+
+pub type ChannelId = &'static str;
+pub type BaseToken = &'static str;
+
+pub type MintTokenLimit = token::Amount;
+pub type ThroughtputTokenLimit = token::Amount;
+
+const MINT_LIMIT: MintTokenLimit = MintTokenLimit::from_u128(10_000_000_000_000_000); // 10B NAM -> Just a higher number than total supply for safety
+const THROUGHPUT_LIMIT: ThroughtputTokenLimit =
+    ThroughtputTokenLimit::from_u128(20_000_000_000_000); // 20M NAM
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {
+    // 1. Enable IBC rate limits for native token
+    let native_token = ctx.get_native_token()?;
+    let mint_limit_token_key = ibc::mint_limit_key(&native_token);
+    let throughput_limit_token_key = ibc::throughput_limit_key(&native_token);
+
+    ctx.write(&mint_limit_token_key, MINT_LIMIT)?;
+    ctx.write(&throughput_limit_token_key, THROUGHPUT_LIMIT)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{assert_snapshot, MockCtx};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = MockCtx::default();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/phase5b.snap"),
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! A copy of the `update_ibc_rate_limits` proposal, applied to any storage.
+
+use namada_ibc::storage::{mint_limit_key, throughput_limit_key};
+use namada_tx_prelude::token::Amount;
+use namada_tx_prelude::*;
+
+pub type ChannelId = &'static str;
+pub type BaseDenom = &'static str;
+
+pub type MintLimit = Amount;
+pub type ThroughputLimit = Amount;
+
+// An example token IBC trace string
+const BASE_DENOM: &str = "transfer/08-wasm-1369/0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+pub const IBC_TOKENS: [(ChannelId, BaseDenom, MintLimit, ThroughputLimit); 1] = [(
+    "channel-9", // example channel
+    BASE_DENOM,
+    Amount::from_u64(10000), // example value
+    Amount::from_u64(10000), // example value
+)];
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {
+    for (channel_id, base_token, mint_limit, throughput_limit) in IBC_TOKENS {
+        let ibc_denom = format!("transfer/{channel_id}/{base_token}");
+        let token_address = ibc::ibc_token(&ibc_denom);
+        let key_mint_limit = mint_limit_key(&token_address);
+        let key_throughput_limit = throughput_limit_key(&token_address);
+        ctx.write(&key_mint_limit, mint_limit)?;
+        ctx.write(&key_throughput_limit, throughput_limit)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{assert_snapshot, MockCtx};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = MockCtx::default();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/snapshots/update_ibc_rate_limits.snap"
+            ),
+        );
+    }
+}
@@ -0,0 +1,36 @@
+#!/bin/bash
+
+set -e
+
+if [[ $# -ne 2 ]]; then
+  echo "Usage: $0 <wasm name, e.g. pre_phase4> <expected hash>"
+  exit 1
+fi
+
+wasm_name="$1"
+expected_hash=$(echo "$2" | tr '[:lower:]' '[:upper:]')
+file="artifacts/$wasm_name.wasm"
+
+# Rebuild the artifacts in the pinned container so that the build is
+# deterministic
+earthly +build
+
+if [[ ! -f "$file" ]]; then
+  echo "No artifact found at $file."
+  exit 1
+fi
+
+if command -v sha256sum > /dev/null; then
+  actual_hash=$(sha256sum "$file" | cut -d ' ' -f 1)
+else
+  actual_hash=$(shasum -a 256 "$file" | cut -d ' ' -f 1)
+fi
+actual_hash=$(echo "$actual_hash" | tr '[:lower:]' '[:upper:]')
+
+echo "Rebuilt Code Hash: $actual_hash"
+if [[ "$actual_hash" == "$expected_hash" ]]; then
+  echo "The rebuilt $wasm_name.wasm matches the expected hash."
+else
+  echo "The rebuilt $wasm_name.wasm DOES NOT match the expected hash $expected_hash."
+  exit 1
+fi
@@ -0,0 +1,29 @@
+#!/bin/bash
+
+set -e
+
+folder_path="target/wasm32-unknown-unknown/release"
+# The maximum size in bytes of the code of a governance proposal
+budget="${WASM_SIZE_BUDGET:-300000}"
+failed=0
+
+# Loop through all .wasm files in the folder
+for file in "$folder_path"/*.wasm; do
+  # Check if the file exists (in case no .wasm files are found)
+  if [[ -f "$file" ]]; then
+    file_name=$(basename "$file")
+    size=$(wc -c < "$file" | tr -d ' ')
+
+    if (( size > budget )); then
+      echo "$file_name is $size bytes, which exceeds the budget of $budget bytes"
+      failed=1
+    else
+      echo "$file_name is $size bytes"
+    fi
+  else
+    echo "No .wasm files found in the directory."
+    exit 1
+  fi
+done
+
+exit $failed
@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    #[clap(long, env)]
+    pub tendermint_url: String,
+
+    /// Where to export the storage queried from the node, in the format read
+    /// by the simulator
+    #[clap(long, env)]
+    pub dump_to: Option<PathBuf>,
+}
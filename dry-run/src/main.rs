@@ -0,0 +1,82 @@
+//! Query the storage keys that the pre-phase4 migration reads from a running
+//! full node, compute the planned writes locally over them, and render a
+//! before and after comparison of every affected key.
+//!
+//! The keys to query are discovered by executing the migration: the keys it
+//! reads are fetched from the node and the migration is executed again over
+//! them, until it reads no new keys.
+
+pub mod config;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use clap::Parser;
+use config::AppConfig;
+use namada_sdk::rpc::{query_epoch, query_native_token, query_storage_value_bytes};
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+use tendermint_rpc::HttpClient;
+use test_utils::{write_state_export, MockCtx, StorageAccess};
+
+#[tokio::main]
+async fn main() {
+    let config = AppConfig::parse();
+
+    let client = HttpClient::new(config.tendermint_url.as_str()).unwrap();
+    let native_token = query_native_token(&client).await.unwrap();
+    let native_token = Address::decode(native_token.encode()).unwrap();
+    let epoch = query_epoch(&client).await.unwrap();
+
+    let mut queried = BTreeSet::new();
+    let mut initial = BTreeMap::new();
+    let ctx = loop {
+        let mut ctx = MockCtx::new(native_token.clone(), initial.clone());
+        ctx.block_epoch = Epoch(epoch.0);
+        pre_phase4_core::apply(&mut ctx).unwrap();
+
+        let read_keys: BTreeSet<Key> = ctx
+            .accesses()
+            .into_iter()
+            .filter_map(|access| match access {
+                StorageAccess::Read { key, .. } => Some(key),
+                _ => None,
+            })
+            .filter(|key| !queried.contains(key))
+            .collect();
+        if read_keys.is_empty() {
+            break ctx;
+        }
+        for key in read_keys {
+            // The SDK may depend on a different build of the storage types
+            let sdk_key = namada_sdk::storage::Key::parse(key.to_string()).unwrap();
+            let (value, _proof) = query_storage_value_bytes(&client, &sdk_key, None, false)
+                .await
+                .unwrap();
+            if let Some(value) = value {
+                initial.insert(key.clone(), value);
+            }
+            queried.insert(key);
+        }
+    };
+
+    let mut changed = 0;
+    for (key, value) in ctx.diff() {
+        let before = initial
+            .get(&key)
+            .map(hex::encode)
+            .unwrap_or_else(|| "(none)".to_owned());
+        let after = value
+            .map(hex::encode)
+            .unwrap_or_else(|| "(deleted)".to_owned());
+        println!("{key}\n  before: {before}\n  after:  {after}");
+        changed += 1;
+    }
+    println!(
+        "Queried {} keys from the node, {changed} of which would change",
+        queried.len()
+    );
+
+    if let Some(path) = config.dump_to {
+        write_state_export(&initial, path).unwrap();
+    }
+}
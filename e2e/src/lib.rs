@@ -0,0 +1,243 @@
+//! End-to-end test of the pre-phase4 proposal on a single-validator localnet
+//! running in a Docker container.
+//!
+//! The test submits the optimized wasm as a governance proposal, votes for it
+//! with the validator, waits for its activation, and checks every key that
+//! the migration should have written byte for byte against the writes planned
+//! over the storage of the localnet before the proposal.
+//!
+//! It is ignored by default. Run it with
+//! `cargo test -p e2e -- --ignored` after `earthly +build`, with
+//! `NAMADA_LOCALNET_IMAGE` naming an image that starts a localnet with epochs
+//! of a few seconds and a minimal governance voting period. As the wasm
+//! refuses to execute on any chain but the one it was built for, build it with
+//! `NAMADA_NETWORK` naming a network of `pre-phase4-core/networks.toml` that has the
+//! chain ID of the localnet.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use namada_sdk::rpc::{query_epoch, query_native_token, query_storage_value_bytes};
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+use tendermint_rpc::HttpClient;
+use test_utils::{MockCtx, StorageAccess};
+
+/// The name of the container running the localnet
+pub const CONTAINER: &str = "namada-governance-e2e";
+/// The RPC address of the localnet, as published by the container
+pub const TENDERMINT_URL: &str = "http://127.0.0.1:26657";
+/// The alias of the validator account in the wallet of the container
+pub const VALIDATOR: &str = "validator-0";
+
+/// Run `docker` with the given arguments and return its standard output
+pub fn docker(args: &[&str]) -> String {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .expect("unable to run docker");
+    assert!(
+        output.status.success(),
+        "docker {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("docker output is not UTF-8")
+}
+
+/// Run `namadac` inside the localnet container with the given arguments
+pub fn namadac(args: &[&str]) -> String {
+    let mut docker_args = vec!["exec", CONTAINER, "namadac"];
+    docker_args.extend_from_slice(args);
+    docker(&docker_args)
+}
+
+/// A running localnet container, removed when dropped
+pub struct Localnet;
+
+impl Localnet {
+    /// Start a localnet from the given image and wait for its first block
+    pub async fn start(image: &str) -> (Self, HttpClient) {
+        docker(&[
+            "run",
+            "--detach",
+            "--rm",
+            "--name",
+            CONTAINER,
+            "--publish",
+            "26657:26657",
+            image,
+        ]);
+        let localnet = Self;
+        let client = HttpClient::new(TENDERMINT_URL).unwrap();
+        for _ in 0..60 {
+            if query_epoch(&client).await.is_ok() {
+                return (localnet, client);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        panic!("the localnet did not start within a minute");
+    }
+}
+
+impl Drop for Localnet {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .args(["rm", "--force", CONTAINER])
+            .output();
+    }
+}
+
+/// Wait until the chain reaches the given epoch
+pub async fn wait_for_epoch(client: &HttpClient, epoch: u64) {
+    while query_epoch(client).await.unwrap().0 < epoch {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Query the value of the given key
+pub async fn query(client: &HttpClient, key: &Key) -> Option<Vec<u8>> {
+    // The SDK may depend on a different build of the storage types
+    let sdk_key = namada_sdk::storage::Key::parse(key.to_string()).unwrap();
+    let (value, _proof) = query_storage_value_bytes(client, &sdk_key, None, false)
+        .await
+        .unwrap();
+    value
+}
+
+/// Compute the writes of the migration over the current storage of the chain,
+/// querying the keys it reads until it reads no new keys
+pub async fn planned_writes(client: &HttpClient, epoch: u64) -> BTreeMap<Key, Option<Vec<u8>>> {
+    let native_token = query_native_token(client).await.unwrap();
+    let native_token = Address::decode(native_token.encode()).unwrap();
+    let mut queried = BTreeSet::new();
+    let mut initial = BTreeMap::new();
+    loop {
+        let mut ctx = MockCtx::new(native_token.clone(), initial.clone());
+        ctx.block_epoch = Epoch(epoch);
+        pre_phase4_core::apply(&mut ctx).unwrap();
+
+        let read_keys: BTreeSet<Key> = ctx
+            .accesses()
+            .into_iter()
+            .filter_map(|access| match access {
+                StorageAccess::Read { key, .. } => Some(key),
+                _ => None,
+            })
+            .filter(|key| !queried.contains(key))
+            .collect();
+        if read_keys.is_empty() {
+            return ctx.diff();
+        }
+        for key in read_keys {
+            if let Some(value) = query(client, &key).await {
+                initial.insert(key.clone(), value);
+            }
+            queried.insert(key);
+        }
+    }
+}
+
+/// Write a proposal executing the given wasm, in the format read by
+/// `namadac init-proposal`, to the given path
+pub fn write_proposal(
+    path: &Path,
+    wasm: &[u8],
+    author: &str,
+    voting_start_epoch: u64,
+    voting_end_epoch: u64,
+    activation_epoch: u64,
+) {
+    let proposal = serde_json::json!({
+        "proposal": {
+            "content": {
+                "title": "Reset the MASP conversions",
+                "authors": "e2e",
+                "abstract": "End-to-end test of the pre-phase4 proposal",
+            },
+            "author": author,
+            "voting_start_epoch": voting_start_epoch,
+            "voting_end_epoch": voting_end_epoch,
+            "activation_epoch": activation_epoch,
+        },
+        "data": wasm,
+    });
+    std::fs::write(path, serde_json::to_vec_pretty(&proposal).unwrap()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires docker, a localnet image, and the built wasm"]
+    async fn test_proposal_on_localnet() {
+        let image = std::env::var("NAMADA_LOCALNET_IMAGE")
+            .expect("NAMADA_LOCALNET_IMAGE must name a localnet image");
+        let wasm_path = std::env::var("E2E_WASM").unwrap_or_else(|_| {
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../artifacts/pre_phase4.wasm").to_owned()
+        });
+        let wasm = std::fs::read(&wasm_path).unwrap();
+
+        let (_localnet, client) = Localnet::start(&image).await;
+
+        // Submit the proposal, leaving the minimal voting period and grace
+        // period of the localnet
+        let author = namadac(&["find", "--alias", VALIDATOR, "--addr"]);
+        let author = author
+            .split_whitespace()
+            .find(|word| word.starts_with("tnam"))
+            .expect("the validator has no address")
+            .to_owned();
+        let epoch = query_epoch(&client).await.unwrap().0;
+        let (voting_start_epoch, voting_end_epoch, activation_epoch) =
+            (epoch + 2, epoch + 5, epoch + 7);
+        let proposal_path = std::env::temp_dir().join("e2e-proposal.json");
+        write_proposal(
+            &proposal_path,
+            &wasm,
+            &author,
+            voting_start_epoch,
+            voting_end_epoch,
+            activation_epoch,
+        );
+        docker(&[
+            "cp",
+            proposal_path.to_str().unwrap(),
+            &format!("{CONTAINER}:/tmp/proposal.json"),
+        ]);
+        namadac(&[
+            "init-proposal",
+            "--data-path",
+            "/tmp/proposal.json",
+            "--signing-keys",
+            VALIDATOR,
+        ]);
+
+        // Plan the writes before the proposal can be executed
+        let expected_writes = planned_writes(&client, activation_epoch).await;
+        assert!(!expected_writes.is_empty());
+
+        // Vote for the proposal with the only validator
+        wait_for_epoch(&client, voting_start_epoch).await;
+        namadac(&[
+            "vote-proposal",
+            "--proposal-id",
+            "0",
+            "--vote",
+            "yay",
+            "--address",
+            VALIDATOR,
+            "--signing-keys",
+            VALIDATOR,
+        ]);
+
+        // Check the storage once the proposal is executed
+        wait_for_epoch(&client, activation_epoch + 1).await;
+        for (key, expected) in &expected_writes {
+            assert_eq!(&query(&client, key).await, expected, "{key} differs");
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    /// The gas limit the transaction must fit into, e.g. the governance
+    /// execution gas limit
+    #[clap(long, env)]
+    pub gas_limit: Option<u64>,
+
+    /// The optimized pre-phase4 WASM to execute, by default the one built by
+    /// `earthly +build`
+    #[clap(long, env = "SMOKE_WASM", default_value = wasm_smoke::ARTIFACT)]
+    pub wasm: PathBuf,
+
+    /// The exported chain state to execute the transaction over, with a line
+    /// per key holding the key and the hex encoding of its value, instead of
+    /// an empty storage
+    #[clap(long, env)]
+    pub state: Option<PathBuf>,
+}
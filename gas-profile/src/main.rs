@@ -0,0 +1,136 @@
+//! Execute the optimized pre-phase4 WASM in the runtime of the node and report
+//! the gas it uses, as metered by the node, so that proposers can confirm that
+//! the transaction fits into the gas limit before submitting it. The storage
+//! accesses are also broken down by token and by kind of access, priced with
+//! the node's gas costs per byte, by replaying the migration over an in-memory
+//! storage holding the same state.
+
+pub mod config;
+
+use std::collections::BTreeMap;
+use std::process;
+
+use clap::Parser;
+use config::AppConfig;
+use namada_gas::{Gas, GasMetering, STORAGE_ACCESS_GAS_PER_BYTE, STORAGE_WRITE_GAS_PER_BYTE};
+use namada_tx_prelude::storage::Key;
+use test_utils::{MockCtx, StorageAccess};
+
+/// The gas used by a group of storage accesses
+#[derive(Default)]
+struct GasUsage {
+    reads: u64,
+    writes: u64,
+    deletes: u64,
+    gas: u64,
+}
+
+impl GasUsage {
+    fn add(&mut self, access: &StorageAccess) {
+        let gas = access_gas(access);
+        match access {
+            StorageAccess::Read { .. } => self.reads += 1,
+            StorageAccess::Write { .. } => self.writes += 1,
+            StorageAccess::Delete { .. } => self.deletes += 1,
+        }
+        self.gas += gas;
+    }
+}
+
+/// Price a storage access with the node's gas costs per byte of the key and
+/// value
+fn access_gas(access: &StorageAccess) -> u64 {
+    let key_len = |key: &Key| key.len() as u64;
+    match access {
+        StorageAccess::Read { key, len } => {
+            (key_len(key) + *len as u64) * STORAGE_ACCESS_GAS_PER_BYTE
+        }
+        StorageAccess::Write { key, len } => {
+            (key_len(key) + *len as u64) * STORAGE_WRITE_GAS_PER_BYTE
+        }
+        StorageAccess::Delete { key } => key_len(key) * STORAGE_WRITE_GAS_PER_BYTE,
+    }
+}
+
+fn main() {
+    let config = AppConfig::parse();
+
+    let state = match &config.state {
+        Some(path) => test_utils::read_state_export(path)
+            .unwrap_or_else(|error| panic!("unable to read {}: {error}", path.display())),
+        None => BTreeMap::new(),
+    };
+    let wasm = std::fs::read(&config.wasm)
+        .unwrap_or_else(|error| panic!("unable to read {}: {error}", config.wasm.display()));
+
+    // Meter the execution of the WASM in the runtime of the node
+    let (env, result) = wasm_smoke::execute_over(wasm, &state);
+    if let Err(error) = result {
+        println!("The transaction failed in the runtime of the node: {error}");
+        process::exit(1)
+    }
+    let gas_scale = env.gas_meter.borrow().get_gas_scale();
+    let whole_gas = |gas: u64| u64::from(Gas::from(gas).get_whole_gas_units(gas_scale));
+    let tx_gas = whole_gas(env.gas_meter.borrow().get_tx_consumed_gas().into());
+
+    // Replay the migration over the same state to break its storage accesses
+    // down
+    let native_token = env.state.in_mem().native_token.clone();
+    let mut ctx = MockCtx::new(native_token, state);
+    let summaries = pre_phase4_core::apply(&mut ctx).unwrap();
+
+    // Attribute every access to the token whose conversions it concerns, if
+    // any
+    let token_of = |key: &Key| {
+        summaries
+            .iter()
+            .find(|summary| summary.keys.contains(key))
+            .map(|summary| summary.token.to_string())
+            .unwrap_or_else(|| "(other)".to_owned())
+    };
+    let mut per_token: BTreeMap<String, GasUsage> = BTreeMap::new();
+    let mut total = GasUsage::default();
+    for access in ctx.accesses() {
+        let key = match &access {
+            StorageAccess::Read { key, .. }
+            | StorageAccess::Write { key, .. }
+            | StorageAccess::Delete { key } => key,
+        };
+        per_token.entry(token_of(key)).or_default().add(&access);
+        total.add(&access);
+    }
+
+    println!("token,epochs,reads,writes,deletes,storage_gas,storage_gas_per_epoch");
+    for (token, usage) in &per_token {
+        let epochs: u64 = summaries
+            .iter()
+            .filter(|summary| &summary.token.to_string() == token)
+            .map(|summary| summary.epochs)
+            .sum();
+        let storage_gas = whole_gas(usage.gas);
+        let gas_per_epoch = storage_gas.checked_div(epochs).unwrap_or_default();
+        println!(
+            "{token},{epochs},{},{},{},{storage_gas},{gas_per_epoch}",
+            usage.reads, usage.writes, usage.deletes
+        );
+    }
+    println!(
+        "total,,{},{},{},{},",
+        total.reads,
+        total.writes,
+        total.deletes,
+        whole_gas(total.gas)
+    );
+    println!("The transaction uses {tx_gas} gas in the runtime of the node");
+
+    if let Some(gas_limit) = config.gas_limit {
+        if tx_gas > gas_limit {
+            println!("The transaction exceeds the gas limit of {gas_limit}");
+            process::exit(1)
+        }
+        println!(
+            "The transaction uses {:.2}% of the gas limit of {gas_limit}",
+            tx_gas as f64 * 100.0 / gas_limit as f64
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Measure the time and the number of allocations taken to plan the
+//! conversions of the migration, as a function of the target epoch and of the
+//! number of tokens. Run with `cargo bench -p pre-phase4-core`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use namada_tx_prelude::masp::MaspEpoch;
+use namada_tx_prelude::token::Denomination;
+use namada_tx_prelude::*;
+// Link the native host functions imported by the transaction
+use namada_tests as _;
+
+/// An allocator counting the allocations made through it
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The MASP epoch of the mainnet target epoch
+const MAINNET_TARGET_EPOCH: u64 = 211;
+/// The number of tokens reset on mainnet
+const MAINNET_TOKENS: usize = 10;
+
+fn masp_epoch(epoch: u64) -> MaspEpoch {
+    MaspEpoch::try_from_epoch(Epoch(epoch), 1).unwrap()
+}
+
+/// Get a table of the given number of distinct tokens
+fn tokens(count: usize) -> Vec<(Address, Denomination, u128)> {
+    (0..count)
+        .map(|index| {
+            let ibc_denom = format!("transfer/channel-{index}/utoken");
+            (
+                ibc::ibc_token(ibc_denom).clone(),
+                Denomination(0u8),
+                100_000_000,
+            )
+        })
+        .collect()
+}
+
+/// Plan the conversions of the given tokens for the given target epoch,
+/// resetting every epoch before it
+fn plan(tokens: &[(Address, Denomination, u128)], target_epoch: u64) -> usize {
+    let native = (
+        Address::decode(test_utils::NATIVE_TOKEN).unwrap(),
+        Denomination(6u8),
+        1_000_000_000,
+    );
+    let target_masp_epoch = masp_epoch(target_epoch);
+    let reset_epochs = (MaspEpoch::zero(), masp_epoch(target_epoch - 1));
+    pre_phase4_core::conversion_matrix(tokens, &native, &target_masp_epoch, &reset_epochs)
+        .unwrap()
+        .len()
+}
+
+/// Print the number of allocations made by a single plan, which criterion
+/// does not measure
+fn report_allocations(name: &str, tokens: &[(Address, Denomination, u128)], target_epoch: u64) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let conversions = plan(tokens, target_epoch);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{name}: {conversions} conversions, {allocations} allocations");
+}
+
+fn bench_target_epochs(c: &mut Criterion) {
+    let tokens = tokens(MAINNET_TOKENS);
+    let mut group = c.benchmark_group("conversion_matrix/target_epoch");
+    for target_epoch in [16, 64, MAINNET_TARGET_EPOCH, 512] {
+        report_allocations(
+            &format!("target epoch {target_epoch}"),
+            &tokens,
+            target_epoch,
+        );
+        group.bench_with_input(
+            BenchmarkId::from_parameter(target_epoch),
+            &target_epoch,
+            |b, target_epoch| b.iter(|| plan(&tokens, *target_epoch)),
+        );
+    }
+    group.finish();
+}
+
+fn bench_token_counts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conversion_matrix/tokens");
+    for count in [1, 5, MAINNET_TOKENS, 20] {
+        let tokens = tokens(count);
+        report_allocations(&format!("{count} tokens"), &tokens, MAINNET_TARGET_EPOCH);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &tokens, |b, tokens| {
+            b.iter(|| plan(tokens, MAINNET_TARGET_EPOCH))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    // Planning the larger tables takes seconds
+    config = Criterion::default().sample_size(10);
+    targets = bench_target_epochs, bench_token_counts
+}
+criterion_main!(benches);
@@ -0,0 +1,232 @@
+//! Compile the constants of the network selected by the `NAMADA_NETWORK`
+//! environment variable, as listed in `networks.toml`, and its token table
+//! into the `TOKENS` constant, so that reviewers of a proposal diff data files
+//! rather than Rust source while a malformed table still fails the build.
+//! Tokens listed by symbol take their channel ID, base denomination, and
+//! denomination from the token registry shared by the proposals, so that the
+//! tables of the proposals cannot drift apart.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The file listing the networks that the migration can be built for
+const NETWORKS_FILE: &str = "networks.toml";
+/// The network built for when `NAMADA_NETWORK` is not set
+const DEFAULT_NETWORK: &str = "mainnet";
+/// The token registry shared by the proposals
+const REGISTRY_FILE: &str = "../token-registry.json";
+
+/// A network that the migration can be built for
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Network {
+    chain_id: String,
+    native_token: String,
+    /// The file holding the token table of the network
+    tokens: String,
+}
+
+/// A token of the registry
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistryToken {
+    symbol: String,
+    channel: String,
+    base: String,
+    denomination: u8,
+    /// The Namada address of the token, checked against the address derived
+    /// from the channel and the base denomination by `check-registry`
+    #[allow(dead_code)]
+    address: String,
+}
+
+/// The token table
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokensFile {
+    tokens: Vec<TokenEntry>,
+}
+
+/// A token of the table
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenEntry {
+    /// The symbol of a token of the registry
+    symbol: Option<String>,
+    /// The Namada address of a token missing from the registry
+    address: Option<String>,
+    /// The denomination of a token missing from the registry
+    denomination: Option<u8>,
+    precision: u64,
+    /// The inclusive range of MASP epochs whose conversions are reset, if not
+    /// every epoch before the target epoch
+    epochs: Option<(u64, u64)>,
+}
+
+impl TokenEntry {
+    /// Get the expression constructing the `TokenAddress` of this token and
+    /// its denomination
+    fn resolve(&self, index: usize, registry: &BTreeMap<&str, &RegistryToken>) -> (String, u8) {
+        match (&self.symbol, &self.address, self.denomination) {
+            (Some(symbol), None, None) => {
+                let token = registry
+                    .get(symbol.as_str())
+                    .unwrap_or_else(|| panic!("token {index}: {symbol} is not in the registry"));
+                (
+                    format!("TokenAddress::Ibc({:?}, {:?})", token.channel, token.base),
+                    token.denomination,
+                )
+            }
+            (None, Some(address), Some(denomination)) => {
+                assert!(
+                    address.starts_with("tnam1"),
+                    "token {index}: {address} is not a Namada address"
+                );
+                (format!("TokenAddress::Address({address:?})"), denomination)
+            }
+            _ => panic!(
+                "token {index}: expected either a symbol of the registry, or an address and a \
+                 denomination"
+            ),
+        }
+    }
+}
+
+/// Read the registry and check that its symbols and IBC tokens are unique and
+/// its channel IDs well formed
+fn read_registry() -> Vec<RegistryToken> {
+    let contents = fs::read_to_string(REGISTRY_FILE)
+        .unwrap_or_else(|error| panic!("unable to read {REGISTRY_FILE}: {error}"));
+    let registry: Vec<RegistryToken> = serde_json::from_str(&contents)
+        .unwrap_or_else(|error| panic!("unable to parse {REGISTRY_FILE}: {error}"));
+
+    let mut symbols = BTreeSet::new();
+    let mut denoms = BTreeSet::new();
+    for token in &registry {
+        assert!(
+            symbols.insert(&token.symbol),
+            "{REGISTRY_FILE}: {} is listed more than once",
+            token.symbol
+        );
+        assert!(
+            denoms.insert((&token.channel, &token.base)),
+            "{REGISTRY_FILE}: {}/{} is listed more than once",
+            token.channel,
+            token.base
+        );
+        let channel_number = token.channel.strip_prefix("channel-").unwrap_or_default();
+        assert!(
+            !channel_number.is_empty() && channel_number.bytes().all(|b| b.is_ascii_digit()),
+            "{REGISTRY_FILE}: {} is not a channel ID",
+            token.channel
+        );
+        assert!(
+            !token.base.is_empty() && !token.base.contains('/'),
+            "{REGISTRY_FILE}: {:?} is not a base denomination",
+            token.base
+        );
+    }
+    registry
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={NETWORKS_FILE}");
+    println!("cargo:rerun-if-changed={REGISTRY_FILE}");
+    println!("cargo:rerun-if-env-changed=NAMADA_NETWORK");
+    let network_name = env::var("NAMADA_NETWORK").unwrap_or_else(|_| DEFAULT_NETWORK.to_owned());
+    let contents = fs::read_to_string(NETWORKS_FILE)
+        .unwrap_or_else(|error| panic!("unable to read {NETWORKS_FILE}: {error}"));
+    let mut networks: BTreeMap<String, Network> = toml::from_str(&contents)
+        .unwrap_or_else(|error| panic!("unable to parse {NETWORKS_FILE}: {error}"));
+    let network = networks.remove(&network_name).unwrap_or_else(|| {
+        panic!("NAMADA_NETWORK is {network_name}, which is not a network of {NETWORKS_FILE}")
+    });
+    assert!(
+        network.native_token.starts_with("tnam1"),
+        "{network_name}: {} is not a Namada address",
+        network.native_token
+    );
+
+    let registry = read_registry();
+    let mut symbol_entries = String::new();
+    for token in &registry {
+        writeln!(
+            symbol_entries,
+            "    ({:?}, {:?}),",
+            token.symbol, token.base
+        )
+        .unwrap();
+    }
+    let registry_len = registry.len();
+    let registry: BTreeMap<_, _> = registry
+        .iter()
+        .map(|token| (token.symbol.as_str(), token))
+        .collect();
+    println!("cargo:rerun-if-changed={}", network.tokens);
+    let contents = fs::read_to_string(&network.tokens)
+        .unwrap_or_else(|error| panic!("unable to read {}: {error}", network.tokens));
+    let table: TokensFile = toml::from_str(&contents)
+        .unwrap_or_else(|error| panic!("unable to parse {}: {error}", network.tokens));
+
+    let mut addresses = BTreeSet::new();
+    let mut entries = String::new();
+    let mut epoch_entries = String::new();
+    let mut windows = 0;
+    for (index, token) in table.tokens.iter().enumerate() {
+        let (token_address, denomination) = token.resolve(index, &registry);
+        assert!(
+            token.precision > 0,
+            "token {index}: the precision must be positive"
+        );
+        assert!(
+            addresses.insert(token_address.clone()),
+            "token {index}: {token_address} is listed more than once"
+        );
+        writeln!(
+            entries,
+            "    ({token_address}, Denomination({denomination}u8), {}),",
+            token.precision
+        )
+        .unwrap();
+        if let Some((from_epoch, to_epoch)) = token.epochs {
+            assert!(
+                from_epoch <= to_epoch,
+                "token {index}: the epoch range must be ordered"
+            );
+            writeln!(
+                epoch_entries,
+                "    ({token_address}, ({from_epoch}, {to_epoch})),"
+            )
+            .unwrap();
+            windows += 1;
+        }
+    }
+
+    let generated = format!(
+        "/// The network that the constants of this migration are selected for
+pub const NETWORK: &str = {network_name:?};
+/// The ID of the chain that the constants of this migration are configured
+/// for
+pub const CHAIN_ID: &str = {:?};
+// The address of the native token. This is what rewards are denominated in.
+const NATIVE_TOKEN_BECH32M: AddressBech32m = {:?};
+// The non-native tokens whose conversions will be reset
+pub const TOKENS: [(TokenAddress, Denomination, Precision); {}] = [\n{entries}];
+// The tokens of TOKENS whose reset is limited to a range of MASP epochs
+pub const TOKEN_EPOCHS: [(TokenAddress, EpochWindow); {windows}] = [\n{epoch_entries}];
+// The symbols of the token registry along with their base denominations, by
+// which symbolic token references are resolved through the MASP token map
+pub const REGISTRY_SYMBOLS: [(&str, BaseToken); {registry_len}] = [\n{symbol_entries}];\n",
+        network.chain_id,
+        network.native_token,
+        table.tokens.len()
+    );
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("network.rs"), generated)
+        .expect("unable to write the generated network constants");
+}
@@ -0,0 +1,70 @@
+//! Feed arbitrary token tables and epochs into the conversion planner, looking
+//! for panics, overflows, and non-deterministic output. Run with
+//! `cargo fuzz run conversion_matrix` from the `pre-phase4-core` directory.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use namada_tx_prelude::masp::MaspEpoch;
+use namada_tx_prelude::token::Denomination;
+use namada_tx_prelude::*;
+// Link the native host functions imported by the transaction
+use namada_tests as _;
+
+/// A token of the table, identified by the channel and base token of its IBC
+/// denomination
+#[derive(Arbitrary, Debug)]
+struct TokenSpec {
+    channel: u8,
+    base_token: u8,
+    denomination: u8,
+    precision: u64,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    tokens: Vec<TokenSpec>,
+    native_denomination: u8,
+    native_precision: u64,
+    target_epoch: u8,
+    window: Option<(u8, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let masp_epoch = |epoch| MaspEpoch::try_from_epoch(Epoch(u64::from(epoch)), 1).unwrap();
+    // Only plans of well-formed windows are meaningful
+    if input.target_epoch == 0 {
+        return;
+    }
+    let target_masp_epoch = masp_epoch(input.target_epoch);
+    let reset_epochs = match input.window {
+        Some((start, end)) if start <= end && end < input.target_epoch => {
+            (masp_epoch(start), masp_epoch(end))
+        }
+        Some(_) => return,
+        None => (MaspEpoch::zero(), masp_epoch(input.target_epoch - 1)),
+    };
+    let tokens: Vec<_> = input
+        .tokens
+        .iter()
+        .take(8)
+        .map(|token| {
+            let ibc_denom = format!("transfer/channel-{}/u{}", token.channel, token.base_token);
+            (
+                ibc::ibc_token(ibc_denom).clone(),
+                Denomination(token.denomination),
+                u128::from(token.precision),
+            )
+        })
+        .collect();
+    let native = (
+        Address::decode("tnam1q9gr66cvu4hrzm0sd5kmlnjje82gs3xlfg3v6nu7").unwrap(),
+        Denomination(input.native_denomination),
+        u128::from(input.native_precision),
+    );
+
+    let plan =
+        |_| pre_phase4_core::conversion_matrix(&tokens, &native, &target_masp_epoch, &reset_epochs).ok();
+    assert_eq!(plan(0), plan(1), "the conversion plan is not deterministic");
+});
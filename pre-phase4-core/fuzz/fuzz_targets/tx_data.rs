@@ -0,0 +1,22 @@
+//! Execute the transaction with arbitrary bytes as its data, looking for
+//! panics in the decoding of the proposal data. Malformed data must make the
+//! transaction fail cleanly, as a panic aborts the governance execution. Run
+//! with `cargo fuzz run tx_data` from the `pre-phase4-core` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use namada_tests::tx::{ctx, tx_host_env};
+use namada_tx_prelude::*;
+
+fuzz_target!(|data: Vec<u8>| {
+    tx_host_env::init();
+    // Run on the chain of the build, so that the data is decoded at all
+    tx_host_env::with(|env| {
+        env.state.in_mem_mut().chain_id = chain::ChainId(pre_phase4_core::CHAIN_ID.to_owned())
+    });
+    let mut tx = Tx::from_type(transaction::TxType::Raw);
+    tx.add_serialized_data(data);
+    // Either outcome is fine, as long as the transaction does not panic
+    let _ = pre_phase4_core::execute(ctx(), tx.batch_first_tx());
+});
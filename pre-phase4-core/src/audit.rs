@@ -0,0 +1,84 @@
+//! A storage adapter recording every write of the migration, in order, as the
+//! key written and the hash of the value written, so that auditors can diff
+//! the writes of two builds of the proposal without comparing whole values.
+//!
+//! The log is append-only: every write and delete adds an entry, including
+//! repeated writes of the same key, so that it reflects the exact sequence of
+//! writes rather than only their final outcome.
+
+use namada_tx_prelude::hash::Hash;
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+use storage_adapters::{Adapted, Adapter};
+
+/// A single write of the migration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The key written
+    pub key: Key,
+    /// The hash of the value written, or `None` if the key was deleted
+    pub value_hash: Option<Hash>,
+}
+
+/// An adapter recording every write passed through to the inner storage
+pub struct AuditLog<'ctx, S> {
+    inner: &'ctx mut S,
+    entries: Vec<AuditEntry>,
+}
+
+impl<'ctx, S> AuditLog<'ctx, S> {
+    /// Start an empty log over the given storage
+    pub fn new(inner: &'ctx mut S) -> Adapted<Self> {
+        Adapted(Self {
+            inner,
+            entries: Vec::new(),
+        })
+    }
+
+    /// Get the writes recorded so far, in the order they were made
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Stop recording and get the recorded writes
+    pub fn into_entries(self) -> Vec<AuditEntry> {
+        self.entries
+    }
+}
+
+/// Render the given writes with a line per write holding the key and the hash
+/// of the value, or `deleted`, so that the logs of two builds can be compared
+/// with a plain text diff
+pub fn render(entries: &[AuditEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| match &entry.value_hash {
+            Some(value_hash) => format!("{} {value_hash}\n", entry.key),
+            None => format!("{} deleted\n", entry.key),
+        })
+        .collect()
+}
+
+impl<S: StorageRead + StorageWrite> Adapter for AuditLog<'_, S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        self.inner
+    }
+
+    fn write_bytes(&mut self, key: &Key, value: &[u8]) -> Result<()> {
+        self.entries.push(AuditEntry {
+            key: key.clone(),
+            value_hash: Some(Hash::sha256(value)),
+        });
+        self.inner.write_bytes(key, value)
+    }
+
+    fn delete(&mut self, key: &Key) -> Result<()> {
+        self.entries.push(AuditEntry {
+            key: key.clone(),
+            value_hash: None,
+        });
+        self.inner.delete(key)
+    }
+}
@@ -0,0 +1,213 @@
+//! The effective configuration of the migration: the tokens to reset and the
+//! epochs and native token to reset them against. It is resolved from the
+//! constants compiled into the WASM and the upgrade spec of the proposal, or
+//! built directly by the networks reusing this crate with their own tokens.
+
+use masp::{MaspEpoch, Precision};
+use namada_tx_prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use token::Denomination;
+
+use crate::{
+    check_target_epoch, current_precision, discover_tokens, pair_precisions, stored_denomination,
+    token_of_symbol, ApplicationMode, EpochWindow, PrecisionMode, RewardScale, TokenOperation,
+    UpgradeSpec, APPLICATION_MODE, DELISTED_TOKENS, DISCOVER_TOKENS, KEEP_PRECISIONS,
+    NATIVE_TOKEN_BECH32M, NATIVE_TOKEN_DENOMINATION, NATIVE_TOKEN_PRECISION, PAIRED_TOKENS,
+    RESET_EPOCHS, SCALED_TOKENS, TOKENS, TOKEN_EPOCHS,
+};
+
+/// The configuration of a migration. The tables of the reinstated and migrated
+/// tokens are always those compiled into the WASM. The configuration applied by
+/// every migration is stored under `migration_config_key` for auditing.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub struct Config {
+    /// The address of the native token. This is what rewards are denominated
+    /// in.
+    pub native_token: Address,
+    /// The denomination of the native token
+    pub native_denomination: Denomination,
+    /// The new reward precision of the native token
+    pub native_precision: Precision,
+    /// The MASP epoch in which the migration will be applied
+    pub target_epoch: MaspEpoch,
+    /// The window of MASP epochs whose conversions are reset, or `None` to
+    /// reset every epoch before the target epoch
+    pub reset_epochs: Option<EpochWindow>,
+    /// The non-native tokens to reset, given as their address, denomination,
+    /// and new reward precision
+    pub tokens: Vec<(Address, Denomination, Precision)>,
+    /// The windows of MASP epochs narrowing down the reset of the above tokens
+    /// that were onboarded after the first reset epochs
+    pub token_epochs: BTreeMap<Address, EpochWindow>,
+    /// The tokens whose scheduled conversions have their rewards scaled rather
+    /// than erased, given as their address, denomination, and scale factor
+    pub scaled_tokens: Vec<(Address, Denomination, RewardScale)>,
+    /// The tokens removed from the shielded pool, given as their address,
+    /// denomination, and reward precision
+    pub delisted_tokens: Vec<(Address, Denomination, Precision)>,
+    /// Whether the reward precisions are written to the live keys or
+    /// scheduled for the target epoch
+    pub application_mode: ApplicationMode,
+}
+
+impl Config {
+    /// Resolve the configuration of the migration from the compiled constants,
+    /// with the settings of the given specification overriding them if any.
+    /// Fails if the specification does not fit the chain.
+    pub fn resolve(ctx: &impl StorageRead, spec: Option<&UpgradeSpec>) -> Result<Self> {
+        let native_token = match spec.and_then(|spec| spec.native_token.as_ref()) {
+            Some(native_token) => {
+                let chain_native_token = ctx.get_native_token()?;
+                if *native_token != chain_native_token {
+                    return Err(Error::new_alloc(format!(
+                        "the configured native token {native_token} is not the native token \
+                         {chain_native_token} of the chain"
+                    )));
+                }
+                native_token.clone()
+            }
+            None => Address::from_str(NATIVE_TOKEN_BECH32M)
+                .expect("unable to construct native token address"),
+        };
+        // This number controls the number of epochs of conversions created
+        let target_epoch = match spec.and_then(|spec| spec.target_epoch) {
+            Some(target_epoch) => {
+                check_target_epoch(ctx, &target_epoch)?;
+                target_epoch
+            }
+            None => MaspEpoch::try_from_epoch(Epoch(844), 4).map_err(Error::new_const)?,
+        };
+        let reset_epochs = spec.and_then(|spec| spec.reset_epochs).or(RESET_EPOCHS);
+
+        // Determine the tokens to reset, rescale, and prune
+        let mut token_epochs = BTreeMap::new();
+        let (mut tokens, scaled_tokens, delisted_tokens) = if let Some(token_specs) =
+            spec.and_then(|spec| spec.tokens.as_ref())
+        {
+            let mut tokens = Vec::new();
+            let mut scaled_tokens = Vec::new();
+            let mut delisted_tokens = Vec::new();
+            let mut addresses = BTreeSet::new();
+            for token in token_specs {
+                let address = match (&token.address, &token.symbol) {
+                    (Some(address), None) => address.clone(),
+                    (None, Some(symbol)) => token_of_symbol(ctx, symbol)?,
+                    _ => {
+                        return Err(Error::new_const(
+                            "every token must be given by either its address or its symbol",
+                        ))
+                    }
+                };
+                // The native token is reset by its own routine
+                if address == native_token {
+                    return Err(Error::new_alloc(format!(
+                        "token {address} is the native token, whose conversions are reset \
+                         separately, and must not be listed"
+                    )));
+                }
+                // A symbol may resolve to a token that is also listed by address
+                if !addresses.insert(address.clone()) {
+                    return Err(Error::new_alloc(format!(
+                        "token {address} is listed more than once"
+                    )));
+                }
+                let denomination = match token.denomination {
+                    Some(denomination) => stored_denomination(ctx, &address, denomination)?,
+                    None => ctx
+                        .read::<Denomination>(&token::storage_key::denom_key(&address))?
+                        .ok_or_else(|| {
+                            Error::new_alloc(format!(
+                            "token {address} has no denomination, neither configured nor stored"
+                        ))
+                        })?,
+                };
+                if let Some(epochs) = token.epochs {
+                    token_epochs.insert(address.clone(), epochs);
+                }
+                match token.operation.unwrap_or(TokenOperation::Reset) {
+                    TokenOperation::Reset => tokens.push((address, denomination, token.precision)),
+                    TokenOperation::Rescale(scale) => {
+                        scaled_tokens.push((address, denomination, scale))
+                    }
+                    TokenOperation::Prune => {
+                        delisted_tokens.push((address, denomination, token.precision))
+                    }
+                }
+            }
+            (tokens, scaled_tokens, delisted_tokens)
+        } else {
+            let tokens = if DISCOVER_TOKENS {
+                discover_tokens(ctx, &native_token)?
+            } else {
+                TOKENS
+                    .iter()
+                    .map(|(token_address, denomination, precision)| {
+                        (token_address.to_address(), *denomination, *precision)
+                    })
+                    .collect()
+            };
+            token_epochs.extend(
+                TOKEN_EPOCHS
+                    .iter()
+                    .map(|(token_address, epochs)| (token_address.to_address(), *epochs)),
+            );
+            let scaled_tokens = SCALED_TOKENS
+                .iter()
+                .map(|(token_address, denomination, scale)| {
+                    (token_address.to_address(), *denomination, *scale)
+                })
+                .collect();
+            let delisted_tokens = DELISTED_TOKENS
+                .iter()
+                .map(|(token_address, denomination, precision)| {
+                    (token_address.to_address(), *denomination, *precision)
+                })
+                .collect();
+            (tokens, scaled_tokens, delisted_tokens)
+        };
+        if let Some(exclude) = spec.and_then(|spec| spec.exclude.as_ref()) {
+            for token_address in exclude {
+                if !tokens
+                    .iter()
+                    .any(|(address, _, _)| address == token_address)
+                {
+                    return Err(Error::new_alloc(format!(
+                        "the excluded token {token_address} is not reset by this migration"
+                    )));
+                }
+            }
+            tokens.retain(|(address, _, _)| !exclude.contains(address));
+        }
+        pair_precisions(&mut tokens, &PAIRED_TOKENS)?;
+        let keep_precisions = match spec.and_then(|spec| spec.precision_mode) {
+            Some(precision_mode) => precision_mode == PrecisionMode::Keep,
+            None => KEEP_PRECISIONS,
+        };
+        let native_precision = if keep_precisions {
+            for (token_address, _, precision) in &mut tokens {
+                *precision = current_precision(ctx, token_address, *precision)?;
+            }
+            current_precision(ctx, &native_token, NATIVE_TOKEN_PRECISION)?
+        } else {
+            NATIVE_TOKEN_PRECISION
+        };
+        let application_mode = spec
+            .and_then(|spec| spec.application_mode)
+            .unwrap_or(APPLICATION_MODE);
+
+        Ok(Self {
+            native_token,
+            native_denomination: NATIVE_TOKEN_DENOMINATION,
+            native_precision,
+            target_epoch,
+            reset_epochs,
+            tokens,
+            token_epochs,
+            scaled_tokens,
+            delisted_tokens,
+            application_mode,
+        })
+    }
+}
@@ -0,0 +1,76 @@
+//! A storage adapter keeping every write of the migration in memory instead of
+//! committing it, so that a rehearsal proposal can go through the whole
+//! governance flow on a testnet with no effect on its state. The planned
+//! writes are logged by the transaction, one line per write.
+//!
+//! Reads see the planned writes first and fall back to the real keys, so the
+//! migration behaves exactly as it would if its writes were committed. The
+//! inner storage is only ever borrowed immutably, so nothing can reach it.
+
+use std::collections::BTreeMap;
+
+use namada_tx_prelude::hash::Hash;
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+use storage_adapters::{Adapted, Adapter};
+
+/// An adapter planning its writes over a storage it never writes to
+pub struct DryRun<'ctx, S> {
+    inner: &'ctx S,
+    writes: BTreeMap<Key, Option<Vec<u8>>>,
+    log: Vec<String>,
+}
+
+impl<'ctx, S> DryRun<'ctx, S> {
+    /// Start planning writes over the given storage
+    pub fn new(inner: &'ctx S) -> Adapted<Self> {
+        Adapted(Self {
+            inner,
+            writes: BTreeMap::new(),
+            log: Vec::new(),
+        })
+    }
+
+    /// Get the final value planned for every written key, or `None` for the
+    /// deleted keys
+    pub fn writes(&self) -> &BTreeMap<Key, Option<Vec<u8>>> {
+        &self.writes
+    }
+
+    /// Get a line per planned write, in the order they were made, holding the
+    /// key and the hash and length of the value, or `delete` and the key
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+}
+
+impl<S: StorageRead> Adapter for DryRun<'_, S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        self.inner
+    }
+
+    fn read_bytes(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        match self.writes.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.inner.read_bytes(key),
+        }
+    }
+
+    fn write_bytes(&mut self, key: &Key, value: &[u8]) -> Result<()> {
+        self.log.push(format!(
+            "dry-run write {key} {} {}",
+            Hash::sha256(value),
+            value.len()
+        ));
+        self.writes.insert(key.clone(), Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &Key) -> Result<()> {
+        self.log.push(format!("dry-run delete {key}"));
+        self.writes.insert(key.clone(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+//! Events summarizing the conversion reset applied to each token, so that
+//! indexers and explorers can display what the migration changed without
+//! replaying it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use masp::{MaspEpoch, Precision};
+use masp_primitives::convert::AllowedConversion;
+use namada_events::extend::EventAttributeEntry;
+use namada_events::{Event, EventLevel, EventToEmit, EventType};
+use namada_tx_prelude::hash::Hash;
+use namada_tx_prelude::*;
+use sha2::{Digest, Sha256};
+use token::MaspDigitPos;
+
+/// The type of the events emitted for every reset token
+pub const CONVERSION_RESET: EventType = EventType::new("masp-migration/conversion-reset");
+
+/// A summary of the conversion reset applied to a single token. It holds an
+/// entry for every key written, so it grows with the numbers of epochs and
+/// digits reset.
+#[derive(Debug)]
+pub struct ResetSummary {
+    /// The address of the reset token
+    pub token: Address,
+    /// The reward precision scheduled for the token, if any
+    pub precision: Option<Precision>,
+    /// The number of epochs whose conversions were written
+    pub epochs: u64,
+    /// The first and last epochs whose conversions were written, if any
+    pub epoch_range: Option<(MaspEpoch, MaspEpoch)>,
+    /// The hashes of the written conversions, keyed by their storage key
+    pub conversions: BTreeMap<storage::Key, [u8; 32]>,
+    /// Every key written or deleted for the token, conversions included
+    pub keys: BTreeSet<storage::Key>,
+}
+
+impl ResetSummary {
+    /// Start a summary for the given token
+    pub fn new(token: Address, precision: Option<Precision>) -> Self {
+        Self {
+            token,
+            precision,
+            epochs: 0,
+            epoch_range: None,
+            conversions: BTreeMap::new(),
+            keys: BTreeSet::new(),
+        }
+    }
+
+    /// Record a conversion about to be written for the given epoch and digit
+    /// under the given key
+    pub fn record(
+        &mut self,
+        epoch: MaspEpoch,
+        digit: MaspDigitPos,
+        key: &storage::Key,
+        conversion: &AllowedConversion,
+    ) -> TxResult {
+        // Every epoch has exactly one conversion per digit
+        if digit == MaspDigitPos::Zero {
+            self.epochs += 1;
+        }
+        self.epoch_range = Some(match self.epoch_range {
+            Some((first, last)) => (first.min(epoch), last.max(epoch)),
+            None => (epoch, epoch),
+        });
+        let conversion_hash = Sha256::digest(borsh::to_vec(conversion).map_err(Error::new)?);
+        self.conversions.insert(key.clone(), conversion_hash.into());
+        self.record_key(key);
+        Ok(())
+    }
+
+    /// Record a key other than a conversion about to be written or deleted
+    /// for the token
+    pub fn record_key(&mut self, key: &storage::Key) {
+        self.keys.insert(key.clone());
+    }
+
+    /// Get the hash over all the recorded conversion keys and values
+    pub fn digest(&self) -> Hash {
+        digest_conversions(&self.conversions)
+    }
+}
+
+/// Compute a deterministic hash over the given conversion keys and the hashes
+/// of their values, in key order
+pub fn digest_conversions(conversions: &BTreeMap<storage::Key, [u8; 32]>) -> Hash {
+    let mut hasher = Sha256::new();
+    for (key, conversion_hash) in conversions {
+        hasher.update(key.to_string().as_bytes());
+        hasher.update(conversion_hash);
+    }
+    Hash(hasher.finalize().into())
+}
+
+impl EventToEmit for ResetSummary {
+    const DOMAIN: &'static str = "masp-migration";
+}
+
+impl From<ResetSummary> for Event {
+    fn from(summary: ResetSummary) -> Self {
+        let mut event = Event::new(CONVERSION_RESET, EventLevel::Tx);
+        event
+            .extend(ResetToken(&summary.token))
+            .extend(ResetEpochs(summary.epochs))
+            .extend(ResetConversions(summary.conversions.len() as u64))
+            .extend(ResetDigest(summary.digest().to_string()));
+        if let Some(precision) = summary.precision {
+            event.extend(ResetPrecision(precision));
+        }
+        event
+    }
+}
+
+/// Extend an [`Event`] with the address of the reset token
+pub struct ResetToken<'a>(pub &'a Address);
+
+impl<'a> EventAttributeEntry<'a> for ResetToken<'a> {
+    type Value = &'a Address;
+    type ValueOwned = Address;
+
+    const KEY: &'static str = "token";
+
+    fn into_value(self) -> Self::Value {
+        self.0
+    }
+}
+
+/// Extend an [`Event`] with the scheduled reward precision
+pub struct ResetPrecision(pub Precision);
+
+impl EventAttributeEntry<'static> for ResetPrecision {
+    type Value = Precision;
+    type ValueOwned = Self::Value;
+
+    const KEY: &'static str = "precision";
+
+    fn into_value(self) -> Self::Value {
+        self.0
+    }
+}
+
+/// Extend an [`Event`] with the number of epochs written
+pub struct ResetEpochs(pub u64);
+
+impl EventAttributeEntry<'static> for ResetEpochs {
+    type Value = u64;
+    type ValueOwned = Self::Value;
+
+    const KEY: &'static str = "epochs";
+
+    fn into_value(self) -> Self::Value {
+        self.0
+    }
+}
+
+/// Extend an [`Event`] with the number of conversions written
+pub struct ResetConversions(pub u64);
+
+impl EventAttributeEntry<'static> for ResetConversions {
+    type Value = u64;
+    type ValueOwned = Self::Value;
+
+    const KEY: &'static str = "conversions";
+
+    fn into_value(self) -> Self::Value {
+        self.0
+    }
+}
+
+/// Extend an [`Event`] with the hash over the written conversions
+pub struct ResetDigest(pub String);
+
+impl EventAttributeEntry<'static> for ResetDigest {
+    type Value = String;
+    type ValueOwned = Self::Value;
+
+    const KEY: &'static str = "digest";
+
+    fn into_value(self) -> Self::Value {
+        self.0
+    }
+}
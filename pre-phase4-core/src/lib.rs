@@ -0,0 +1,5327 @@
+//! The migration of the pre-phase4 transaction, which resets the MASP
+//! conversions and reward precisions of shielded tokens. The proposal crate
+//! only wraps [`execute`] into the transaction entrypoint, so that the tools
+//! planning and checking its writes link the same logic as a library. The
+//! keys written by the migration are listed in the documentation of the
+//! proposal crate.
+//!
+//! Conversions are not compacted. The protocol looks conversions up by the
+//! asset type they apply to, so the key schema has no way of sharing a value
+//! between keys, and no two conversions are identical anyway: each one debits
+//! the asset type of its own epoch, even where the accumulated rewards of
+//! consecutive epochs coincide.
+
+#[cfg(any(test, feature = "audit-log"))]
+pub mod audit;
+mod config;
+#[cfg(any(test, feature = "dry-run"))]
+pub mod dry_run;
+mod events;
+mod plan;
+#[cfg(any(test, feature = "shadow"))]
+pub mod shadow;
+mod spec;
+
+pub use config::Config;
+use dec::Dec;
+use events::digest_conversions;
+pub use events::ResetSummary;
+use masp::MaspEpoch;
+use masp::{encode_asset_type, Precision};
+use masp_primitives::asset_type::AssetType;
+use masp_primitives::convert::AllowedConversion;
+use masp_primitives::transaction::components::I128Sum;
+use namada_events::EmitEvents;
+use namada_tx_prelude::*;
+pub use plan::planned_writes;
+pub use spec::{
+    ApplicationMode, PrecisionMode, TokenOperation, TokenSpec, UpgradeSpec, SPEC_VERSION,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use storage::KeySeg;
+use token::storage_key::{
+    masp_base_native_precision_key, masp_conversion_key, masp_reward_precision_key,
+    masp_scheduled_base_native_precision_key, masp_scheduled_reward_precision_key,
+};
+use token::{Denomination, MaspDigitPos};
+
+pub type ChannelId = &'static str;
+pub type BaseToken = &'static str;
+
+/// Represents a Namada address in Bech32m encoding
+pub type AddressBech32m = &'static str;
+
+/// A convenience data structure to allow token addresses to be more readably
+/// expressed as a channel ID and base token instead of a raw Namada address.
+pub enum TokenAddress {
+    // Specify an IBC address. This can also be done more directly using the
+    // Self::Address variant.
+    Ibc(ChannelId, BaseToken),
+    // Directly specify a Namada address
+    Address(AddressBech32m),
+    // Specify a wrapped ERC20 token by its address on Ethereum
+    Erc20(EthAddress),
+}
+
+impl TokenAddress {
+    /// Compute the Namada address of this token
+    pub fn to_address(&self) -> Address {
+        match self {
+            TokenAddress::Ibc(channel_id, base_token) => {
+                let ibc_denom = format!("transfer/{channel_id}/{base_token}");
+                ibc::ibc_token(&ibc_denom).clone()
+            }
+            TokenAddress::Address(addr) => {
+                Address::from_str(addr).expect("unable to construct token address")
+            }
+            TokenAddress::Erc20(eth_address) => {
+                Address::Internal(address::InternalAddress::Erc20(*eth_address))
+            }
+        }
+    }
+}
+
+/// An inclusive range of MASP epochs
+pub type EpochWindow = (u64, u64);
+
+/// An inclusive range of MASP epochs whose conversions are reset
+pub type ResetEpochs = (MaspEpoch, MaspEpoch);
+
+/// The amount of native tokens granted per epoch for every PRECISION units of
+/// a token
+pub type EpochReward = u128;
+
+/// The rewards granted to a token
+pub enum RewardSpec {
+    /// A raw amount of native tokens per MASP epoch for every PRECISION units
+    /// of the token
+    PerEpoch(EpochReward),
+    /// An annualized rate, in basis points, of native token units granted for
+    /// every unit of the token. The per-epoch amount is derived from the
+    /// epoch duration and the token's reward precision in effect at the
+    /// target epoch.
+    AprBasisPoints(u64),
+}
+
+/// A rational factor, expressed as a numerator and a denominator, by which
+/// the rewards in existing conversions are multiplied
+pub type RewardScale = (u64, u64);
+
+// The storage key segment, under the parameters of a token, holding its
+// reward precision history
+const PRECISION_HISTORY_KEY: &str = "masp_precision_history";
+// The storage key segment, under the MASP address, holding the batches of
+// conversions written so far by every batched migration
+const MIGRATION_BATCHES_KEY: &str = "migration_batches";
+// The storage key segment, under the MASP address, holding the digests of
+// the conversions written by every migration
+const MIGRATION_AUDIT_KEY: &str = "migration_audit";
+// The storage key segment, under the MASP address, holding the configuration
+// applied by every migration
+const MIGRATION_CONFIG_KEY: &str = "migration_config";
+// The network, the ID of its chain, the address of its native token, and the
+// non-native tokens whose conversions will be reset, compiled from
+// networks.toml and the token table of the network selected by the
+// NAMADA_NETWORK environment variable at build time.
+include!(concat!(env!("OUT_DIR"), "/network.rs"));
+// The denomination of the native token
+const NATIVE_TOKEN_DENOMINATION: Denomination = Denomination(6u8);
+// The new reward precision of the native token. This also becomes the base
+// native precision.
+const NATIVE_TOKEN_PRECISION: Precision = 1_000_000_000;
+// The base native precision to switch to after the reset, if any. The native
+// conversions scheduled for the target epoch are rescaled from the precision
+// they were written with to this one, so that they remain consistent with the
+// base native precision the protocol applies at the target epoch.
+const RESCALED_BASE_NATIVE_PRECISION: Option<Precision> = None;
+// Whether to also write the identity conversion of the target epoch's own
+// asset types. Some protocol versions expect an explicit conversion entry for
+// the asset types of the latest epoch, while others stop at the epoch before.
+const WRITE_TARGET_EPOCH_CONVERSION: bool = false;
+// The largest number of MASP epochs by which a target epoch supplied with the
+// proposal data may lie ahead of the MASP epoch in which the proposal executes
+const MAX_TARGET_EPOCH_LEAD: u64 = 64;
+// Whether to skip the conversions of the digits above the lowest one for the
+// tokens whose total supply fits into the lowest digit. Notes of such tokens
+// can never hold an amount in the higher digits, so their conversions would
+// never be used. This assumes that the supply does not outgrow the lowest
+// digit before the target epoch.
+const SKIP_EMPTY_DIGITS: bool = false;
+// The window of MASP epochs whose conversions will be reset. If unset, every
+// epoch before the target epoch is reset.
+const RESET_EPOCHS: Option<EpochWindow> = None;
+// The batch of reset epochs written by this proposal, as a number of epochs
+// per batch and the index of the batch. Splitting the reset over several
+// proposals bounds the number of conversion keys written by each one. Every
+// batch writes the conversions of its own epochs under the keys scheduled for
+// the target epoch, and the protocol applies them once the target epoch is
+// reached, whichever proposals wrote them. If unset, every reset epoch is
+// written at once.
+const RESET_BATCH: Option<(u64, u64)> = None;
+// The tokens being removed from the shielded pool. Their notes are converted
+// to the target epoch one last time and their rewards are zeroed.
+const DELISTED_TOKENS: [(TokenAddress, Denomination, Precision); 0] = [];
+// The tokens whose scheduled conversions will have their rewards scaled
+// rather than erased. For example, a factor of (1, 2) halves the rewards.
+const SCALED_TOKENS: [(TokenAddress, Denomination, RewardScale); 0] = [];
+// The tokens whose rewards for the given window of MASP epochs will be
+// granted again on top of their scheduled conversions. This is meant to
+// restore rewards that a previous reset wrongly erased.
+const REINSTATED_TOKENS: [(TokenAddress, Denomination, EpochWindow, RewardSpec); 0] = [];
+// The tokens whose denomination changes, along with their old denomination,
+// new denomination, and precision. Notes of the old denomination's asset types
+// are converted into the new denomination's asset types of the target epoch.
+const DENOMINATION_MIGRATIONS: [(TokenAddress, Denomination, Denomination, Precision); 0] = [];
+// Pairs of a base token and its liquid-staked twin, along with the ratio of
+// the twin's rewards to the base token's rewards. The twin's precision is
+// derived from the base token's precision so that the ratio of their rewards
+// is preserved by the reset, instead of each being reset independently.
+const PAIRED_TOKENS: [(TokenAddress, TokenAddress, RewardScale); 0] = [];
+// Whether to reset every incentivized token found in the MASP token map
+// instead of only the tokens listed in TOKENS. Discovered tokens that are
+// listed in TOKENS take their configured precision, the others keep their
+// current one.
+const DISCOVER_TOKENS: bool = false;
+// Whether to keep the current reward precision of every token and only reset
+// its conversions. The configured precisions then only apply to the tokens
+// that have no reward precision stored yet.
+const KEEP_PRECISIONS: bool = false;
+// Whether the reward precisions are scheduled for the target epoch or written
+// to the live keys right away
+const APPLICATION_MODE: ApplicationMode = ApplicationMode::Scheduled;
+// Whether to skip the tokens whose reset has already been applied, for
+// instance by an earlier proposal that was interrupted part way through the
+// token table. Only the tokens still missing their scheduled precision or
+// conversions are reset.
+const RECONCILE: bool = false;
+// The number of epochs whose asset types are encoded together ahead of the
+// loops computing their conversions
+const ASSET_TYPE_BATCH: usize = 256;
+
+// A sample of asset types, given as their token, denomination, MASP epoch, and
+// digit, along with the hex encoding of their expected identifiers. These are
+// computed ahead of time with the encoding of the targeted protocol version by
+// running the ignored test `print_asset_type_samples`. An upstream change to
+// the encoding would otherwise silently produce conversions for asset types
+// that no note holds.
+#[cfg(any(test, feature = "encoding-check"))]
+const ASSET_TYPE_SAMPLES: [(TokenAddress, Denomination, u64, MaspDigitPos, &str); 22] = [
+    (
+        TokenAddress::Address("tnam1p5z8ruwyu7ha8urhq2l0dhpk2f5dv3ts7uyf2n75"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "9fa69a0f3f6e746016f4ef2580bb37c937682c9fe6a626924b082ba603034c5e",
+    ),
+    (
+        TokenAddress::Address("tnam1p5z8ruwyu7ha8urhq2l0dhpk2f5dv3ts7uyf2n75"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "eb390d78129e1b1a773a83fe6cbd95091e3adc6309eef34e49e58cdfc85a66ba",
+    ),
+    (
+        TokenAddress::Address("tnam1pkg30gnt4q0zn7j00r6hms4ajrxn6f5ysyyl7w9m"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "1a7f85830e8dad588723be244cba970a18643703bb5ceae2316b9ec120072095",
+    ),
+    (
+        TokenAddress::Address("tnam1pkg30gnt4q0zn7j00r6hms4ajrxn6f5ysyyl7w9m"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "1b39b2eb820e2adb88a967cec73bbfb1e2c5230b262262864a0868c09c38b029",
+    ),
+    (
+        TokenAddress::Address("tnam1pklj3kwp0cpsdvv56584rsajty974527qsp8n0nm"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "48fce4a2b7658f64459696db662e72b13e972a07139214a9fb93276becaf21f1",
+    ),
+    (
+        TokenAddress::Address("tnam1pklj3kwp0cpsdvv56584rsajty974527qsp8n0nm"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "255c661c73e832897e8561cac8c499409caeb1e2582621679b014d91966d82b4",
+    ),
+    (
+        TokenAddress::Address("tnam1p4px8sw3am4qvetj7eu77gftm4fz4hcw2ulpldc7"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "152a60aea55bc3da01f1e77cb28f36931285b91a0e882d5646af0e52d9a51c4a",
+    ),
+    (
+        TokenAddress::Address("tnam1p4px8sw3am4qvetj7eu77gftm4fz4hcw2ulpldc7"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "aa25924228c2ffe6f14c7d0889e56c35f52a0b4bdf6d0e94e9e8e76597a5bde0",
+    ),
+    (
+        TokenAddress::Address("tnam1p5z5538v3kdk3wdx7r2hpqm4uq9926dz3ughcp7n"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "051cf324344c24894d0c5e0c352e4dade08e5813e540de18c0122c8252eb808c",
+    ),
+    (
+        TokenAddress::Address("tnam1p5z5538v3kdk3wdx7r2hpqm4uq9926dz3ughcp7n"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "1f7d20265e6b6c3dcfd7b009062bbd140e5c16afdd718baaddd7377424f5b1a4",
+    ),
+    (
+        TokenAddress::Address("tnam1ph6xhf0defk65hm7l5ursscwqdj8ehrcdv300u4g"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "9148f0227957d886815f575a5dfb534e077939c1efc8614e7cc1352d5c8f6b8e",
+    ),
+    (
+        TokenAddress::Address("tnam1ph6xhf0defk65hm7l5ursscwqdj8ehrcdv300u4g"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "795cdc44e962e21d7fdb105171667b88466ab545c79228ff9580d3c5bcc9ee6c",
+    ),
+    (
+        TokenAddress::Address("tnam1pk288t54tg99umhamwx998nh0q2dhc7slch45sqy"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "c08d0464a5c4d369257057c4f3e42684dff2903d86c1852d2c7d36ea7dace3a6",
+    ),
+    (
+        TokenAddress::Address("tnam1pk288t54tg99umhamwx998nh0q2dhc7slch45sqy"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "eff4b7857e7b7e63cc8b19fe734b93eb55612a1bd5236748fe9726fbafd51373",
+    ),
+    (
+        TokenAddress::Address("tnam1pkl64du8p2d240my5umxm24qhrjsvh42ruc98f97"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "5ef9812da1a35bd39dc205f6d9be1249cde75608897e0339f0af9ae47e466105",
+    ),
+    (
+        TokenAddress::Address("tnam1pkl64du8p2d240my5umxm24qhrjsvh42ruc98f97"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "f63d1c828744b95da3b745d03f9cae92ce45b3a556377ead3c88bb10985b38cd",
+    ),
+    (
+        TokenAddress::Address("tnam1phv4vcuw2ftsjahhvg65w4ux8as09tlysuhvzqje"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "12fba7bf5aae657fdf54b88f6240b3673c0e631aad5abfad14608e50527c3cce",
+    ),
+    (
+        TokenAddress::Address("tnam1phv4vcuw2ftsjahhvg65w4ux8as09tlysuhvzqje"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "2fbb25dfbad0197d7bf20e7d53e48f586008f47061e2d5fece7c04b24c4b3286",
+    ),
+    (
+        TokenAddress::Address("tnam1pk6pgu4cpqeu4hqjkt6s724eufu64svpqgu52m3g"),
+        Denomination(0u8),
+        0,
+        MaspDigitPos::Zero,
+        "ee6453207785a95e2f9c3ef04969a49100034a446eb89ec5c304f4dd2b8aa74f",
+    ),
+    (
+        TokenAddress::Address("tnam1pk6pgu4cpqeu4hqjkt6s724eufu64svpqgu52m3g"),
+        Denomination(0u8),
+        1,
+        MaspDigitPos::Three,
+        "103bbcd7ee98da6da83640334dd5bf9a9317f11c614bc203214ddc3342bb7662",
+    ),
+    (
+        TokenAddress::Address("tnam1q9gr66cvu4hrzm0sd5kmlnjje82gs3xlfg3v6nu7"),
+        Denomination(6u8),
+        0,
+        MaspDigitPos::Zero,
+        "30cccca2fbf45f76ad04fd8c05f59039e60bf79f2b56fb26243e1a0a82833f98",
+    ),
+    (
+        TokenAddress::Address("tnam1q9gr66cvu4hrzm0sd5kmlnjje82gs3xlfg3v6nu7"),
+        Denomination(6u8),
+        1,
+        MaspDigitPos::Three,
+        "7682c9b785e6febdf9e0fed38ae0008dbc330745033c5ef87c619238b80736dd",
+    ),
+];
+
+/// Execute the transaction with the given data: apply the migration, with the
+/// specification carried in the data if any, and emit an event summarizing the
+/// reset of every token. This is the body of the entrypoint of the pre-phase4
+/// transaction, which the fuzz targets also feed arbitrary transaction data.
+pub fn execute(ctx: &mut Ctx, tx_data: BatchedTx) -> TxResult {
+    check_chain_id(ctx)?;
+    let spec = UpgradeSpec::from_tx_data(&tx_data)?;
+    #[cfg(not(any(feature = "shadow", feature = "dry-run")))]
+    let summaries = apply_spec(ctx, spec.as_ref())?;
+    #[cfg(all(feature = "shadow", not(feature = "dry-run")))]
+    let summaries = apply_spec(&mut shadow::Shadow::new(ctx), spec.as_ref())?;
+    // A dry run takes precedence over the shadow writes, as it writes nothing
+    #[cfg(feature = "dry-run")]
+    let summaries = {
+        let mut dry_run = dry_run::DryRun::new(&*ctx);
+        let summaries = apply_spec(&mut dry_run, spec.as_ref())?;
+        for line in dry_run.log() {
+            log_string(line);
+        }
+        summaries
+    };
+    for summary in summaries {
+        ctx.emit(summary);
+    }
+
+    Ok(())
+}
+
+/// Refuse to execute on a chain other than the one of the network selected at
+/// build time, so that a testnet artifact can never be applied to mainnet or
+/// the other way around. This is checked before the transaction data is even
+/// decoded.
+fn check_chain_id(ctx: &impl StorageRead) -> TxResult {
+    let chain_id = ctx.get_chain_id()?;
+    if chain_id.as_str() != CHAIN_ID {
+        return Err(Error::new_alloc(format!(
+            "this migration was built for {CHAIN_ID} on the {NETWORK} network, not for \
+             {chain_id}"
+        )));
+    }
+    Ok(())
+}
+
+/// Apply the migration to the given storage and return the summaries of the
+/// conversions written for every token
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> Result<Vec<ResetSummary>> {
+    apply_spec(ctx, None)
+}
+
+/// Apply the migration to the given storage, with the settings of the given
+/// specification overriding the compiled ones if any, and return the
+/// summaries of the conversions written for every token
+pub fn apply_spec<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    spec: Option<&UpgradeSpec>,
+) -> Result<Vec<ResetSummary>> {
+    let config = Config::resolve(ctx, spec)?;
+    apply_config(ctx, &config)
+}
+
+/// Apply the migration with the given configuration to the given storage and
+/// return the summaries of the conversions written for every token
+pub fn apply_config<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    config: &Config,
+) -> Result<Vec<ResetSummary>> {
+    let Config {
+        native_token,
+        native_denomination,
+        native_precision,
+        target_epoch: target_masp_epoch,
+        reset_epochs: reset_window,
+        tokens,
+        token_epochs,
+        scaled_tokens,
+        delisted_tokens,
+        application_mode,
+    } = config.clone();
+    #[cfg(feature = "encoding-check")]
+    check_asset_type_samples(&ASSET_TYPE_SAMPLES)?;
+    // Reject a target epoch or window that leaves no epochs to reset before
+    // writing anything
+    let reset_epochs = reset_epochs(reset_window, &target_masp_epoch)?;
+    let reset_epochs = match RESET_BATCH {
+        Some(batch) => {
+            record_batch(ctx, batch, &target_masp_epoch)?;
+            batch_epochs(batch, &reset_epochs)?
+        }
+        None => reset_epochs,
+    };
+    // Reject precisions that would produce skewed conversions before writing
+    // anything
+    for (token_address, _, precision) in &tokens {
+        // The native token must go through its own reset routine
+        if *token_address == native_token {
+            return Err(Error::new_alloc(format!(
+                "the native token {native_token} must not be in the token table"
+            )));
+        }
+        check_precision(token_address, *precision)?;
+    }
+    check_precision(&native_token, native_precision)?;
+    for (token_address, _, precision) in &delisted_tokens {
+        check_precision(token_address, *precision)?;
+    }
+    for (token_address, _, _, precision) in &DENOMINATION_MIGRATIONS {
+        check_precision(&token_address.to_address(), *precision)?;
+    }
+    // Narrow the reset epochs of the tokens onboarded after the first reset
+    // epochs down to their own windows, rejecting the windows that do not
+    // precede the target epoch before writing anything
+    let tokens = tokens
+        .into_iter()
+        .map(|(token_address, denomination, precision)| {
+            let token_reset_epochs = match token_epochs.get(&token_address) {
+                Some(window) => token_reset_epochs(*window, &reset_epochs, &target_masp_epoch)?,
+                None => Some(reset_epochs),
+            };
+            Ok((token_address, denomination, precision, token_reset_epochs))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // The summaries of the conversions written for every token
+    let mut summaries = Vec::new();
+
+    // Reset the allowed conversions for the above tokens
+    for (token_address, denomination, precision, reset_epochs) in tokens {
+        // Skip the tokens onboarded after the epochs being reset
+        let Some(reset_epochs) = reset_epochs else {
+            continue;
+        };
+        let denomination = stored_denomination(ctx, &token_address, denomination)?;
+        if RECONCILE
+            && token_reset_applied(
+                ctx,
+                &token_address,
+                denomination,
+                precision,
+                &target_masp_epoch,
+                &reset_epochs,
+                application_mode,
+            )?
+        {
+            continue;
+        }
+
+        let summary = reset_token(
+            ctx,
+            &token_address,
+            denomination,
+            precision,
+            &target_masp_epoch,
+            &reset_epochs,
+            application_mode,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Reset the allowed conversions for the native token
+    let native_denomination = stored_denomination(ctx, &native_token, native_denomination)?;
+    let native_reset_applied = RECONCILE
+        && native_reset_applied(
+            ctx,
+            &native_token,
+            native_denomination,
+            native_precision,
+            &target_masp_epoch,
+            &reset_epochs,
+            application_mode,
+        )?;
+    if !native_reset_applied {
+        let summary = reset_native_token(
+            ctx,
+            &native_token,
+            native_denomination,
+            native_precision,
+            &target_masp_epoch,
+            &reset_epochs,
+            application_mode,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Change the base native precision along with the native conversions
+    if let Some(precision) = RESCALED_BASE_NATIVE_PRECISION {
+        let summary = rescale_base_native_precision(
+            ctx,
+            &native_token,
+            native_denomination,
+            precision,
+            &target_masp_epoch,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Scale the rewards of the scheduled conversions of the above tokens
+    for (token_address, denomination, scale) in scaled_tokens {
+        let denomination = stored_denomination(ctx, &token_address, denomination)?;
+        let summary = scale_token(ctx, &token_address, denomination, scale, &target_masp_epoch)?;
+        summaries.push(summary);
+    }
+
+    // Finalize the conversions of the above tokens and stop their rewards
+    for (token_address, denomination, precision) in delisted_tokens {
+        let denomination = stored_denomination(ctx, &token_address, denomination)?;
+        let summary = delist_token(
+            ctx,
+            &token_address,
+            denomination,
+            precision,
+            &target_masp_epoch,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Reinstate the rewards of the above tokens for their epoch windows
+    for (token_address, denomination, window, reward) in REINSTATED_TOKENS {
+        let token_address = token_address.to_address();
+        let denomination = stored_denomination(ctx, &token_address, denomination)?;
+        let precision = target_precision(ctx, &token_address, &target_masp_epoch)?;
+        let reward = epoch_reward(ctx, precision, &reward)?;
+        let summary = reinstate_token(
+            ctx,
+            (&token_address, denomination, precision),
+            (&native_token, native_denomination),
+            window,
+            reward,
+            &target_masp_epoch,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Migrate the asset types of the above tokens to their new denomination
+    for (token_address, old_denomination, new_denomination, precision) in DENOMINATION_MIGRATIONS {
+        let summary = migrate_denomination(
+            ctx,
+            &token_address.to_address(),
+            old_denomination,
+            new_denomination,
+            precision,
+            &target_masp_epoch,
+        )?;
+        summaries.push(summary);
+    }
+
+    // Record a digest of every conversion written for auditing
+    write_migration_digest(ctx, &summaries, &target_masp_epoch, RESET_BATCH)?;
+    write_migration_config(ctx, config)?;
+
+    Ok(summaries)
+}
+
+/// Check that every sampled asset type is encoded into its expected identifier
+#[cfg(any(test, feature = "encoding-check"))]
+fn check_asset_type_samples(
+    samples: &[(TokenAddress, Denomination, u64, MaspDigitPos, &str)],
+) -> TxResult {
+    for (token_address, denomination, epoch, digit, expected) in samples {
+        let asset_type = encode_asset_type(
+            token_address.to_address(),
+            *denomination,
+            *digit,
+            Some(masp_epoch(*epoch)?),
+        )
+        .map_err(Error::new)?;
+        if asset_type.to_string() != *expected {
+            return Err(Error::new_alloc(format!(
+                "the asset type of token {} at MASP epoch {epoch} is encoded as {asset_type} \
+                 instead of {expected}",
+                token_address.to_address(),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Derive the precision of the twin of every given pair from the precision of
+/// its base token, so that the ratio of the twin's rewards to the base
+/// token's rewards is the pair's ratio. Since rewards are distributed per
+/// PRECISION units of a token, the twin's precision is the base precision
+/// divided by the ratio.
+fn pair_precisions(
+    tokens: &mut [(Address, Denomination, Precision)],
+    pairs: &[(TokenAddress, TokenAddress, RewardScale)],
+) -> TxResult {
+    for (base_token, twin_token, ratio) in pairs {
+        let base_token = base_token.to_address();
+        let twin_token = twin_token.to_address();
+        let base_precision = tokens
+            .iter()
+            .find(|(token_address, _, _)| token_address == &base_token)
+            .map(|(_, _, precision)| *precision)
+            .ok_or_else(|| Error::new_const("the base token of a pair is not being reset"))?;
+        let twin_precision = paired_precision(base_precision, *ratio)?;
+        let (_, _, precision) = tokens
+            .iter_mut()
+            .find(|(token_address, _, _)| token_address == &twin_token)
+            .ok_or_else(|| Error::new_const("the twin token of a pair is not being reset"))?;
+        *precision = twin_precision;
+    }
+    Ok(())
+}
+
+/// Compute the precision of a twin token whose rewards relate to those of a
+/// base token with the given precision by the given ratio
+fn paired_precision(
+    base_precision: Precision,
+    (numerator, denominator): RewardScale,
+) -> Result<Precision> {
+    if numerator == 0 {
+        return Err(Error::new_const("the reward ratio of a pair is zero"));
+    }
+    let twin_precision = base_precision
+        .checked_mul(Precision::from(denominator))
+        .ok_or_else(|| Error::new_const("the paired precision overflowed"))?
+        / Precision::from(numerator);
+    if twin_precision == 0 {
+        return Err(Error::new_const("the paired precision is zero"));
+    }
+    Ok(twin_precision)
+}
+
+/// The digests of the conversions written by every migration, keyed by the
+/// target MASP epoch of the migration and its batch of reset epochs, if it is
+/// batched
+pub type MigrationDigests = BTreeMap<(MaspEpoch, Option<(u64, u64)>), hash::Hash>;
+
+/// Get the key holding the digests of the conversions written by every
+/// migration, keyed by the target MASP epoch of the migration and its batch of
+/// reset epochs, if it is batched
+pub fn migration_audit_key() -> storage::Key {
+    storage::Key::from(address::MASP.to_db_key())
+        .push(&MIGRATION_AUDIT_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Record the given conversion for the given epoch and digit in the summary
+/// and write it under the given key, unless the same conversion is already
+/// stored there. The conversion is recorded either way so that the digest of
+/// the migration covers its entire planned output.
+fn write_conversion<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    summary: &mut ResetSummary,
+    epoch: MaspEpoch,
+    digit: MaspDigitPos,
+    conversion_key: &storage::Key,
+    conversion: &AllowedConversion,
+) -> TxResult {
+    summary.record(epoch, digit, conversion_key, conversion)?;
+    if ctx.read::<AllowedConversion>(conversion_key)?.as_ref() == Some(conversion) {
+        return Ok(());
+    }
+    // Write the conversion update to memory
+    ctx.write(conversion_key, conversion.clone())
+}
+
+/// Store a deterministic digest over every conversion key and value written
+/// by this migration, so that anyone can check that the applied state matches
+/// the planned output of the reviewed code. Conversions written more than once
+/// are digested with their final value. The digest is stored next to those of
+/// the other batches of the same target epoch rather than over them, so that
+/// every batch can still be checked once they have all been written.
+fn write_migration_digest<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    summaries: &[ResetSummary],
+    target_masp_epoch: &MaspEpoch,
+    batch: Option<(u64, u64)>,
+) -> TxResult {
+    let mut conversions = BTreeMap::new();
+    for summary in summaries {
+        conversions.extend(
+            summary
+                .conversions
+                .iter()
+                .map(|(key, conversion_hash)| (key.clone(), *conversion_hash)),
+        );
+    }
+    let audit_key = migration_audit_key();
+    let mut digests: MigrationDigests = ctx.read(&audit_key)?.unwrap_or_default();
+    digests.insert(
+        (*target_masp_epoch, batch),
+        digest_conversions(&conversions),
+    );
+    ctx.write(&audit_key, digests)
+}
+
+/// Get the key holding the configuration applied by every migration, keyed by
+/// the target MASP epoch of the migration
+pub fn migration_config_key() -> storage::Key {
+    storage::Key::from(address::MASP.to_db_key())
+        .push(&MIGRATION_CONFIG_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Store the Borsh encoding of the given configuration, with every token
+/// address, epoch, and precision resolved, so that explorers can show exactly
+/// what the migration applied rather than the settings it was given
+fn write_migration_config<S: StorageRead + StorageWrite>(ctx: &mut S, config: &Config) -> TxResult {
+    let config_key = migration_config_key();
+    let mut configs: BTreeMap<MaspEpoch, Config> = ctx.read(&config_key)?.unwrap_or_default();
+    configs.insert(config.target_epoch, config.clone());
+    ctx.write(&config_key, configs)
+}
+
+/// Check that the given precision is compatible with the amounts of a token.
+/// Amounts are split into u64 digits, each converted on its own in multiples
+/// of the precision, so a precision beyond the range of a single digit would
+/// leave the top digit's notes truncated rather than converted.
+fn check_precision(token_address: &Address, precision: Precision) -> TxResult {
+    if precision == 0 {
+        return Err(Error::new_alloc(format!(
+            "the precision of token {token_address} is zero"
+        )));
+    }
+    if precision > Precision::from(u64::MAX) {
+        return Err(Error::new_alloc(format!(
+            "the precision {precision} of token {token_address} exceeds the maximum amount of \
+             a digit"
+        )));
+    }
+    Ok(())
+}
+
+/// Get the denomination stored on chain for the given token, falling back to
+/// the configured one if none is stored. Asset types encoded with any other
+/// denomination than the stored one would not match any existing notes, so a
+/// configured denomination that disagrees with the stored one is an error.
+fn stored_denomination(
+    ctx: &impl StorageRead,
+    token_address: &Address,
+    configured_denomination: Denomination,
+) -> Result<Denomination> {
+    match ctx.read::<Denomination>(&token::storage_key::denom_key(token_address))? {
+        Some(denomination) => {
+            check_denomination(token_address, configured_denomination, denomination)?;
+            Ok(denomination)
+        }
+        None => Ok(configured_denomination),
+    }
+}
+
+/// Get the reward precision currently stored for the given token, falling
+/// back to the configured one if none is stored
+fn current_precision(
+    ctx: &impl StorageRead,
+    token_address: &Address,
+    configured_precision: Precision,
+) -> Result<Precision> {
+    Ok(ctx
+        .read::<Precision>(&masp_reward_precision_key(token_address))?
+        .unwrap_or(configured_precision))
+}
+
+/// Check that a target epoch supplied with the proposal data is after the
+/// current MASP epoch and at most MAX_TARGET_EPOCH_LEAD epochs ahead of it, so
+/// that a mistyped epoch can neither schedule conversions that are never
+/// applied nor ones that lie dormant for years
+fn check_target_epoch(ctx: &impl StorageRead, target_masp_epoch: &MaspEpoch) -> TxResult {
+    let masp_epoch_multiplier: u64 = ctx
+        .read(&parameters_storage::get_masp_epoch_multiplier_key())?
+        .ok_or_else(|| Error::new_const("the MASP epoch multiplier is not set"))?;
+    let block_epoch = ctx.get_block_epoch()?;
+    let current_masp_epoch =
+        MaspEpoch::try_from_epoch(block_epoch, masp_epoch_multiplier).map_err(Error::new_const)?;
+    if *target_masp_epoch <= current_masp_epoch {
+        return Err(Error::new_alloc(format!(
+            "the target epoch {target_masp_epoch} is not after the current MASP epoch \
+             {current_masp_epoch}"
+        )));
+    }
+    let latest_target_epoch =
+        masp_epoch((block_epoch.0 / masp_epoch_multiplier).saturating_add(MAX_TARGET_EPOCH_LEAD))?;
+    if *target_masp_epoch > latest_target_epoch {
+        return Err(Error::new_alloc(format!(
+            "the target epoch {target_masp_epoch} is more than {MAX_TARGET_EPOCH_LEAD} epochs \
+             after the current MASP epoch {current_masp_epoch}"
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the configured denomination of a token agrees with the one
+/// stored on chain
+fn check_denomination(
+    token_address: &Address,
+    configured_denomination: Denomination,
+    stored_denomination: Denomination,
+) -> TxResult {
+    if configured_denomination == stored_denomination {
+        Ok(())
+    } else {
+        Err(Error::new_alloc(format!(
+            "the configured denomination {} of token {token_address} disagrees with the \
+             stored denomination {}",
+            configured_denomination.0, stored_denomination.0,
+        )))
+    }
+}
+
+/// Find every non-native token of the MASP token map with a non-zero maximum
+/// reward rate, along with its stored denomination and the precision it
+/// should be reset with.
+fn discover_tokens(
+    ctx: &impl StorageRead,
+    native_token: &Address,
+) -> Result<Vec<(Address, Denomination, Precision)>> {
+    let token_map_key = token::storage_key::masp_token_map_key();
+    let token_map = ctx
+        .read::<masp::TokenMap>(&token_map_key)?
+        .unwrap_or_default();
+
+    let mut tokens = Vec::new();
+    for token_address in token_map.into_values() {
+        if &token_address == native_token {
+            continue;
+        }
+        // Skip the tokens that are not incentivized
+        let max_reward_rate_key = token::storage_key::masp_max_reward_rate_key(&token_address);
+        let incentivized = ctx
+            .read::<Dec>(&max_reward_rate_key)?
+            .is_some_and(|rate| !rate.is_zero());
+        if !incentivized {
+            continue;
+        }
+        let denomination = ctx
+            .read::<Denomination>(&token::storage_key::denom_key(&token_address))?
+            .ok_or_else(|| Error::new_const("an incentivized token has no denomination"))?;
+        // Prefer the configured precision over the current one
+        let configured = TOKENS
+            .iter()
+            .find(|(configured, _, _)| configured.to_address() == token_address);
+        let precision = match configured {
+            Some((_, configured_denomination, precision)) => {
+                check_denomination(&token_address, *configured_denomination, denomination)?;
+                *precision
+            }
+            None => {
+                let precision_key = masp_reward_precision_key(&token_address);
+                ctx.read::<Precision>(&precision_key)?.ok_or_else(|| {
+                    Error::new_const("an incentivized token has no reward precision")
+                })?
+            }
+        };
+        // Token map entries may alias the same address
+        if !tokens
+            .iter()
+            .any(|(address, _, _)| address == &token_address)
+        {
+            tokens.push((token_address, denomination, precision));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Resolve the token of the given symbol of the token registry through the
+/// MASP token map. The token is looked up by the base denomination of its IBC
+/// denomination rather than by its channel, so that a token moved to another
+/// channel is still found. Fails if the token map holds no token of that base
+/// denomination, or more than one.
+fn token_of_symbol(ctx: &impl StorageRead, symbol: &str) -> Result<Address> {
+    let (_, base_token) = REGISTRY_SYMBOLS
+        .iter()
+        .find(|(registry_symbol, _)| *registry_symbol == symbol)
+        .ok_or_else(|| Error::new_alloc(format!("{symbol} is not in the token registry")))?;
+    let token_map = ctx
+        .read::<masp::TokenMap>(&token::storage_key::masp_token_map_key())?
+        .unwrap_or_default();
+    let addresses: BTreeSet<Address> = token_map
+        .into_iter()
+        .filter(|(denom, _)| {
+            denom.starts_with("transfer/") && denom.rsplit('/').next() == Some(*base_token)
+        })
+        .map(|(_, token_address)| token_address)
+        .collect();
+    let mut addresses = addresses.into_iter();
+    match (addresses.next(), addresses.next()) {
+        (Some(token_address), None) => Ok(token_address),
+        (None, _) => Err(Error::new_alloc(format!(
+            "the MASP token map has no token of {symbol}"
+        ))),
+        (Some(first), Some(second)) => Err(Error::new_alloc(format!(
+            "{symbol} is ambiguous in the MASP token map, which has both {first} and {second}"
+        ))),
+    }
+}
+
+/// Erase the rewards that have been distributed so far for a non-native token
+/// and schedule its new reward precision, or write it right away in the
+/// immediate application mode.
+fn reset_token<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+    application_mode: ApplicationMode,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(token_address.clone(), Some(precision));
+    // The keys holding the current and scheduled shielded reward precision
+    // of current token
+    let shielded_token_reward_precision_key = masp_reward_precision_key(token_address);
+    let shielded_token_scheduled_reward_precision_key =
+        masp_scheduled_reward_precision_key(target_masp_epoch, token_address);
+    let precision_key = application_key(
+        application_mode,
+        &shielded_token_reward_precision_key,
+        &shielded_token_scheduled_reward_precision_key,
+    );
+    summary.record_key(precision_key);
+    summary.record_key(&precision_history_key(token_address));
+
+    let replaced_precision = update_precision(
+        ctx,
+        &shielded_token_reward_precision_key,
+        precision_key,
+        precision,
+    )?;
+    if let Some(replaced_precision) = replaced_precision {
+        record_replaced_precision(ctx, token_address, replaced_precision, target_masp_epoch)?;
+    }
+    let digits = written_digits(ctx, token_address)?;
+    // Write the new TOK conversions to memory
+    for_each_reset_conversion(
+        token_address,
+        denomination,
+        precision,
+        target_masp_epoch,
+        reset_epochs,
+        |epoch, digit, asset_type, reward| {
+            #[cfg(feature = "cross-check")]
+            check_reset_conversion(
+                token_address,
+                denomination,
+                precision,
+                epoch,
+                *target_masp_epoch,
+                digit,
+                reward,
+            )?;
+            if !digits.contains(&digit) {
+                return Ok(());
+            }
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            write_conversion(ctx, &mut summary, epoch, digit, &conversion_key, reward)
+        },
+    )?;
+    if WRITE_TARGET_EPOCH_CONVERSION {
+        write_target_epoch_conversions(
+            ctx,
+            &mut summary,
+            token_address,
+            denomination,
+            target_masp_epoch,
+            &digits,
+        )?;
+    }
+
+    Ok(summary)
+}
+
+/// Encode the asset types TOK[ep, digit] of the given token and digit for the
+/// given epochs, batch by batch into a flat vector, and pass every batch of
+/// epochs along with their asset types, in the given order, to the given
+/// function. The loops over the epochs thus index into asset types encoded up
+/// front rather than encode one on every access, while the batches bound the
+/// memory of the vector however many epochs are given.
+fn for_each_asset_type_batch(
+    token_address: &Address,
+    denomination: Denomination,
+    digit: MaspDigitPos,
+    mut epochs: impl Iterator<Item = MaspEpoch>,
+    mut f: impl FnMut(&[MaspEpoch], &[AssetType]) -> TxResult,
+) -> TxResult {
+    let mut batch = Vec::with_capacity(ASSET_TYPE_BATCH);
+    let mut asset_types = Vec::with_capacity(ASSET_TYPE_BATCH);
+    loop {
+        batch.clear();
+        batch.extend(epochs.by_ref().take(ASSET_TYPE_BATCH));
+        if batch.is_empty() {
+            return Ok(());
+        }
+        asset_types.clear();
+        for epoch in &batch {
+            asset_types.push(
+                encode_asset_type(token_address.clone(), denomination, digit, Some(*epoch))
+                    .map_err(Error::new)?,
+            );
+        }
+        f(&batch, &asset_types)?;
+    }
+}
+
+/// The conversions of the asset types of a token, along with the asset type
+/// they apply to, keyed by the MASP epoch and digit of that asset type
+pub type Conversions = BTreeMap<(MaspEpoch, MaspDigitPos), (AssetType, AllowedConversion)>;
+
+/// Compute the conversions that erase the TOK rewards distributed so far, for
+/// every digit and every epoch of the given reset epochs. This reads no
+/// storage, so the conversion math can be tested in isolation.
+pub fn compute_conversions(
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+) -> Result<Conversions> {
+    let mut conversions = Conversions::new();
+    for_each_reset_conversion(
+        token_address,
+        denomination,
+        precision,
+        target_masp_epoch,
+        reset_epochs,
+        |epoch, digit, asset_type, conversion| {
+            conversions.insert((epoch, digit), (asset_type, conversion.clone()));
+            Ok(())
+        },
+    )?;
+
+    Ok(conversions)
+}
+
+/// Compute the conversions that erase the TOK rewards distributed so far and
+/// pass each of them, along with the epoch, digit, and asset type it applies
+/// to, to the given function, digit by digit and in descending epochs.
+///
+/// The conversion of TOK[ep, digit] is the sum of the reward deltas
+/// -PRECISION TOK[e, digit] + PRECISION TOK[e+1, digit] of every epoch e from
+/// ep up to the epoch preceding the target epoch. The deltas are accumulated
+/// downwards from the last reset epoch, starting from the reward of the epochs
+/// between the reset epochs and the target epoch. Every delta shares its
+/// PRECISION TOK[e+1, digit] with the delta accumulated just before it, so
+/// only that one is carried over rather than a delta memoized for every epoch.
+/// What the given function keeps of the conversions, e.g. the
+/// [`ResetSummary`] of the token, still grows with the number of epochs reset.
+fn for_each_reset_conversion(
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    (first_reset_epoch, last_reset_epoch): &ResetEpochs,
+    mut f: impl FnMut(MaspEpoch, MaspDigitPos, AssetType, &AllowedConversion) -> TxResult,
+) -> TxResult {
+    let precision_amount = i128::try_from(precision)
+        .map_err(|_| Error::new_const("the reward precision does not fit into an i128"))?;
+    for digit in MaspDigitPos::iter() {
+        // TOK[ep, digit]
+        let asset_type = |epoch| {
+            encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+                .map_err(Error::new)
+        };
+        // -PRECISION TOK[ep, digit] + PRECISION TOK[current_ep, digit]
+        let mut reward = window_end_reward(
+            token_address,
+            denomination,
+            precision,
+            target_masp_epoch,
+            last_reset_epoch,
+            digit,
+        )?;
+        // PRECISION TOK[ep+1, digit]
+        let mut next_precision_tok = I128Sum::from_pair(
+            asset_type(next_epoch(*last_reset_epoch)?)?,
+            precision_amount,
+        );
+        for_each_asset_type_batch(
+            token_address,
+            denomination,
+            digit,
+            MaspEpoch::iter_bounds_inclusive(*first_reset_epoch, *last_reset_epoch).rev(),
+            |epochs, asset_types| {
+                for (&epoch, &epoch_asset_type) in epochs.iter().zip(asset_types) {
+                    // PRECISION TOK[ep, digit]
+                    let precision_tok = I128Sum::from_pair(epoch_asset_type, precision_amount);
+                    // -PRECISION TOK[ep, digit] + PRECISION TOK[ep+1, digit]
+                    let reward_delta = -precision_tok.clone() + next_precision_tok.clone();
+                    reward = checked_add_sums(&reward, &reward_delta).ok_or_else(|| {
+                        Error::new_alloc(format!(
+                            "the accumulated reward of token {token_address} overflowed at \
+                             MASP epoch {epoch:?}"
+                        ))
+                    })?;
+                    f(epoch, digit, epoch_asset_type, &reward.clone().into())?;
+                    next_precision_tok = precision_tok;
+                }
+                Ok(())
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Compute the accumulated reward of the epoch following the given last reset
+/// epoch, from which the accumulation of a reset window starts. All the deltas
+/// from that epoch up to the target epoch telescope into:
+/// -PRECISION TOK[last_ep+1, digit] + PRECISION TOK[current_ep, digit]
+fn window_end_reward(
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    last_reset_epoch: &MaspEpoch,
+    digit: MaspDigitPos,
+) -> Result<I128Sum> {
+    let following_epoch = next_epoch(*last_reset_epoch)?;
+    if following_epoch == *target_masp_epoch {
+        return Ok(I128Sum::zero());
+    }
+    let precision = i128::try_from(precision)
+        .map_err(|_| Error::new_const("the reward precision does not fit into an i128"))?;
+    let asset_type = |epoch| {
+        encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+            .map_err(Error::new)
+    };
+    Ok(-I128Sum::from_pair(asset_type(following_epoch)?, precision)
+        + I128Sum::from_pair(asset_type(*target_masp_epoch)?, precision))
+}
+
+/// Add two sums with checked arithmetic on every component, returning `None`
+/// if any component overflows
+fn checked_add_sums(lhs: &I128Sum, rhs: &I128Sum) -> Option<I128Sum> {
+    let mut amounts: BTreeMap<AssetType, i128> = lhs
+        .components()
+        .map(|(asset_type, amount)| (*asset_type, *amount))
+        .collect();
+    for (asset_type, amount) in rhs.components() {
+        let entry = amounts.entry(*asset_type).or_insert(0);
+        *entry = entry.checked_add(*amount)?;
+    }
+    // Every asset type occurs once so combining the pairs cannot overflow
+    Some(
+        amounts
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .fold(I128Sum::zero(), |sum, (asset_type, amount)| {
+                sum + I128Sum::from_pair(asset_type, amount)
+            }),
+    )
+}
+
+/// Compute the conversion of TOK[epoch, digit] after a reset from first
+/// principles rather than by accumulating memoized deltas. Every intermediate
+/// epoch cancels out, leaving only the amounts of TOK[epoch, digit] and
+/// TOK[target_epoch, digit]. Amounts are kept as i128 coefficients per asset
+/// type and combined with checked arithmetic.
+#[cfg(any(test, feature = "cross-check"))]
+fn reference_reset_conversion(
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    epoch: MaspEpoch,
+    target_masp_epoch: MaspEpoch,
+    digit: MaspDigitPos,
+) -> Result<BTreeMap<AssetType, i128>> {
+    let precision = i128::try_from(precision)
+        .map_err(|_| Error::new_const("the precision does not fit into an i128"))?;
+    let asset_type = |epoch| {
+        encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+            .map_err(Error::new)
+    };
+    let mut expected = BTreeMap::new();
+    for (asset_type, amount) in [
+        (asset_type(epoch)?, -precision),
+        (asset_type(target_masp_epoch)?, precision),
+    ] {
+        let entry = expected.entry(asset_type).or_insert(0i128);
+        *entry = entry
+            .checked_add(amount)
+            .ok_or_else(|| Error::new_const("reference conversion overflowed"))?;
+    }
+    expected.retain(|_, amount| *amount != 0);
+    Ok(expected)
+}
+
+/// Check that an optimized reset conversion matches the one computed by
+/// [`reference_reset_conversion`].
+#[cfg(any(test, feature = "cross-check"))]
+fn check_reset_conversion(
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    epoch: MaspEpoch,
+    target_masp_epoch: MaspEpoch,
+    digit: MaspDigitPos,
+    conversion: &AllowedConversion,
+) -> TxResult {
+    let expected = reference_reset_conversion(
+        token_address,
+        denomination,
+        precision,
+        epoch,
+        target_masp_epoch,
+        digit,
+    )?;
+    let actual: BTreeMap<AssetType, i128> = I128Sum::from(conversion.clone())
+        .components()
+        .filter(|(_, amount)| **amount != 0)
+        .map(|(asset_type, amount)| (*asset_type, *amount))
+        .collect();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(Error::new_const(
+            "the computed conversion does not match the reference computation",
+        ))
+    }
+}
+
+/// Erase the rewards that have been distributed so far for the native token
+/// and schedule its new reward precision and base native precision, or write
+/// them right away in the immediate application mode.
+///
+/// Unlike other tokens, native token rewards are paid in-kind: the conversion
+/// of NAM[ep] carries both the principal and the reward in the native asset
+/// itself. With rewards erased, the conversion from any epoch collapses to the
+/// principal alone, so it is written directly in its telescoped form.
+fn reset_native_token<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    native_token: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+    application_mode: ApplicationMode,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(native_token.clone(), Some(precision));
+    // The native token's reward precision doubles as the base native
+    // precision used to scale the rewards of every other token
+    let shielded_token_reward_precision_key = masp_reward_precision_key(native_token);
+    let shielded_token_scheduled_reward_precision_key =
+        masp_scheduled_reward_precision_key(target_masp_epoch, native_token);
+    let shielded_token_base_native_precision_key = masp_base_native_precision_key();
+    let shielded_token_scheduled_base_native_precision_key =
+        masp_scheduled_base_native_precision_key(target_masp_epoch);
+    let precision_key = application_key(
+        application_mode,
+        &shielded_token_reward_precision_key,
+        &shielded_token_scheduled_reward_precision_key,
+    );
+    let base_native_precision_key = application_key(
+        application_mode,
+        &shielded_token_base_native_precision_key,
+        &shielded_token_scheduled_base_native_precision_key,
+    );
+    for key in [
+        precision_key,
+        base_native_precision_key,
+        &precision_history_key(native_token),
+    ] {
+        summary.record_key(key);
+    }
+
+    let replaced_precision = update_precision(
+        ctx,
+        &shielded_token_reward_precision_key,
+        precision_key,
+        precision,
+    )?;
+    if let Some(replaced_precision) = replaced_precision {
+        record_replaced_precision(ctx, native_token, replaced_precision, target_masp_epoch)?;
+    }
+    update_precision(
+        ctx,
+        &shielded_token_base_native_precision_key,
+        base_native_precision_key,
+        precision,
+    )?;
+    let digits = written_digits(ctx, native_token)?;
+
+    // Write the new NAM conversions to memory
+    for_each_native_reset_conversion(
+        native_token,
+        denomination,
+        precision,
+        target_masp_epoch,
+        reset_epochs,
+        |epoch, digit, asset_type, conversion| {
+            if !digits.contains(&digit) {
+                return Ok(());
+            }
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            write_conversion(ctx, &mut summary, epoch, digit, &conversion_key, conversion)
+        },
+    )?;
+    if WRITE_TARGET_EPOCH_CONVERSION {
+        write_target_epoch_conversions(
+            ctx,
+            &mut summary,
+            native_token,
+            denomination,
+            target_masp_epoch,
+            &digits,
+        )?;
+    }
+
+    Ok(summary)
+}
+
+/// Check whether the reset of the given non-native token has already been
+/// applied, i.e. whether its precision is scheduled and every conversion it
+/// writes is already stored with the same value
+fn token_reset_applied(
+    ctx: &impl StorageRead,
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+    application_mode: ApplicationMode,
+) -> Result<bool> {
+    if !precision_applied(
+        ctx,
+        token_address,
+        precision,
+        target_masp_epoch,
+        application_mode,
+    )? {
+        return Ok(false);
+    }
+    let digits = written_digits(ctx, token_address)?;
+    let mut applied = true;
+    for_each_reset_conversion(
+        token_address,
+        denomination,
+        precision,
+        target_masp_epoch,
+        reset_epochs,
+        |_epoch, digit, asset_type, conversion| {
+            if applied && digits.contains(&digit) {
+                applied = conversion_applied(ctx, target_masp_epoch, &asset_type, conversion)?;
+            }
+            Ok(())
+        },
+    )?;
+    Ok(applied)
+}
+
+/// Check whether the reset of the native token has already been applied, i.e.
+/// whether its precision is scheduled and every conversion it writes is
+/// already stored with the same value
+fn native_reset_applied(
+    ctx: &impl StorageRead,
+    native_token: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+    application_mode: ApplicationMode,
+) -> Result<bool> {
+    if !precision_applied(
+        ctx,
+        native_token,
+        precision,
+        target_masp_epoch,
+        application_mode,
+    )? {
+        return Ok(false);
+    }
+    let digits = written_digits(ctx, native_token)?;
+    let mut applied = true;
+    for_each_native_reset_conversion(
+        native_token,
+        denomination,
+        precision,
+        target_masp_epoch,
+        reset_epochs,
+        |_epoch, digit, asset_type, conversion| {
+            if applied && digits.contains(&digit) {
+                applied = conversion_applied(ctx, target_masp_epoch, &asset_type, conversion)?;
+            }
+            Ok(())
+        },
+    )?;
+    Ok(applied)
+}
+
+/// Check whether the given reward precision is either scheduled for the
+/// target epoch or already in effect without a different one being scheduled.
+/// In the immediate application mode, only the precision in effect counts.
+fn precision_applied(
+    ctx: &impl StorageRead,
+    token_address: &Address,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    application_mode: ApplicationMode,
+) -> Result<bool> {
+    if application_mode == ApplicationMode::Immediate {
+        return Ok(
+            ctx.read::<Precision>(&masp_reward_precision_key(token_address))? == Some(precision),
+        );
+    }
+    let scheduled_key = masp_scheduled_reward_precision_key(target_masp_epoch, token_address);
+    match ctx.read::<Precision>(&scheduled_key)? {
+        Some(scheduled_precision) => Ok(scheduled_precision == precision),
+        None => Ok(
+            ctx.read::<Precision>(&masp_reward_precision_key(token_address))? == Some(precision),
+        ),
+    }
+}
+
+/// Check whether the given conversion of the given asset type is already
+/// scheduled for the target epoch
+fn conversion_applied(
+    ctx: &impl StorageRead,
+    target_masp_epoch: &MaspEpoch,
+    asset_type: &AssetType,
+    conversion: &AllowedConversion,
+) -> Result<bool> {
+    let conversion_key = masp_conversion_key(target_masp_epoch, asset_type);
+    Ok(ctx.read::<AllowedConversion>(&conversion_key)?.as_ref() == Some(conversion))
+}
+
+/// Compute the conversions that erase the NAM rewards distributed so far and
+/// pass each of them, along with the epoch, digit, and asset type it applies
+/// to, to the given function. The conversion of NAM[ep, digit] is
+/// -PRECISION NAM[ep, digit] + PRECISION NAM[target_epoch, digit], built from
+/// asset types encoded once per digit.
+fn for_each_native_reset_conversion(
+    native_token: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+    (first_reset_epoch, last_reset_epoch): &ResetEpochs,
+    mut f: impl FnMut(MaspEpoch, MaspDigitPos, AssetType, &AllowedConversion) -> TxResult,
+) -> TxResult {
+    let precision = i128::try_from(precision)
+        .map_err(|_| Error::new_const("the reward precision does not fit into an i128"))?;
+    for digit in MaspDigitPos::iter() {
+        // PRECISION NAM[target_epoch, digit]
+        let target_precision_nam = I128Sum::from_pair(
+            encode_asset_type(
+                native_token.clone(),
+                denomination,
+                digit,
+                Some(*target_masp_epoch),
+            )
+            .map_err(Error::new)?,
+            precision,
+        );
+        for_each_asset_type_batch(
+            native_token,
+            denomination,
+            digit,
+            MaspEpoch::iter_bounds_inclusive(*first_reset_epoch, *last_reset_epoch),
+            |epochs, asset_types| {
+                // NAM[ep, digit]
+                for (&epoch, &asset_type) in epochs.iter().zip(asset_types) {
+                    let mut conversion = -I128Sum::from_pair(asset_type, precision);
+                    conversion += target_precision_nam.clone();
+                    f(epoch, digit, asset_type, &conversion.into())?;
+                }
+                Ok(())
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Compute every conversion that the reset of the given non-native tokens and
+/// of the native token writes, keyed by its storage key, without touching the
+/// storage. Tokens are given as their address, denomination, and precision.
+pub fn conversion_matrix(
+    tokens: &[(Address, Denomination, Precision)],
+    (native_token, native_denomination, native_precision): &(Address, Denomination, Precision),
+    target_masp_epoch: &MaspEpoch,
+    reset_epochs: &ResetEpochs,
+) -> Result<BTreeMap<storage::Key, AllowedConversion>> {
+    let mut matrix = BTreeMap::new();
+    let mut insert = |asset_type, conversion: &AllowedConversion| {
+        matrix.insert(
+            masp_conversion_key(target_masp_epoch, &asset_type),
+            conversion.clone(),
+        );
+        Ok(())
+    };
+    for (token_address, denomination, precision) in tokens {
+        for_each_reset_conversion(
+            token_address,
+            *denomination,
+            *precision,
+            target_masp_epoch,
+            reset_epochs,
+            |_epoch, _digit, asset_type, conversion| insert(asset_type, conversion),
+        )?;
+        if WRITE_TARGET_EPOCH_CONVERSION {
+            for_each_target_epoch_conversion(
+                token_address,
+                *denomination,
+                target_masp_epoch,
+                |_digit, asset_type, conversion| insert(asset_type, conversion),
+            )?;
+        }
+    }
+    for_each_native_reset_conversion(
+        native_token,
+        *native_denomination,
+        *native_precision,
+        target_masp_epoch,
+        reset_epochs,
+        |_epoch, _digit, asset_type, conversion| insert(asset_type, conversion),
+    )?;
+    if WRITE_TARGET_EPOCH_CONVERSION {
+        for_each_target_epoch_conversion(
+            native_token,
+            *native_denomination,
+            target_masp_epoch,
+            |_digit, asset_type, conversion| insert(asset_type, conversion),
+        )?;
+    }
+
+    Ok(matrix)
+}
+
+/// Remove a token from the shielded rewards. Terminal conversions map every
+/// dated asset type of the token to the target epoch so that holders can
+/// still exit, and the maximum reward rate and the gains of the token are
+/// zeroed so that no further rewards accrue. The incentive parameters are kept
+/// rather than deleted: the token stays in the MASP token map, and the node
+/// expects every parameter of the tokens of that map when it updates the
+/// conversions at the next MASP epoch.
+fn delist_token<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    token_address: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(token_address.clone(), Some(precision));
+    // Write the terminal TOK conversions to memory
+    for_each_reset_conversion(
+        token_address,
+        denomination,
+        precision,
+        target_masp_epoch,
+        &reset_epochs(None, target_masp_epoch)?,
+        |epoch, digit, asset_type, conversion| {
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            write_conversion(ctx, &mut summary, epoch, digit, &conversion_key, conversion)
+        },
+    )?;
+
+    // Zero the MASP incentive parameters that drive the rewards of the token
+    for key in [
+        token::storage_key::masp_max_reward_rate_key(token_address),
+        token::storage_key::masp_kp_gain_key(token_address),
+        token::storage_key::masp_kd_gain_key(token_address),
+    ] {
+        summary.record_key(&key);
+        ctx.write(&key, Dec::zero())?;
+    }
+
+    Ok(summary)
+}
+
+/// Write the given precision under the given key, either the scheduled or the
+/// live one, unless the precision currently stored under the live key is
+/// already equal to it. A different precision scheduled earlier is then
+/// deleted, so that it does not override the one in effect at the target
+/// epoch. Returns the precision that is being replaced, if any.
+fn update_precision<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    current_key: &storage::Key,
+    precision_key: &storage::Key,
+    precision: Precision,
+) -> Result<Option<Precision>> {
+    let current_precision = ctx.read::<Precision>(current_key)?;
+    if current_precision == Some(precision) {
+        if precision_key != current_key
+            && ctx
+                .read::<Precision>(precision_key)?
+                .is_some_and(|scheduled_precision| scheduled_precision != precision)
+        {
+            ctx.delete(precision_key)?;
+        }
+        return Ok(None);
+    }
+    ctx.write(precision_key, precision)?;
+    Ok(current_precision)
+}
+
+/// Get the key that the given application mode writes a value to: the live
+/// key in the immediate mode, or the key scheduled for the target epoch
+fn application_key<'key>(
+    application_mode: ApplicationMode,
+    live_key: &'key storage::Key,
+    scheduled_key: &'key storage::Key,
+) -> &'key storage::Key {
+    match application_mode {
+        ApplicationMode::Scheduled => scheduled_key,
+        ApplicationMode::Immediate => live_key,
+    }
+}
+
+/// Get the key holding the precision history of the given token. The history
+/// maps every MASP epoch at which the reward precision of the token was
+/// replaced to the precision that was in effect before that epoch, so that
+/// conversions of earlier epochs can still be interpreted.
+pub fn precision_history_key(token_address: &Address) -> storage::Key {
+    token::storage_key::parameter_prefix(token_address)
+        .push(&PRECISION_HISTORY_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Record in the precision history of the given token that the given
+/// precision stops being in effect at the target epoch
+fn record_replaced_precision<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    token_address: &Address,
+    replaced_precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+) -> TxResult {
+    let history_key = precision_history_key(token_address);
+    let mut history: BTreeMap<MaspEpoch, Precision> = ctx.read(&history_key)?.unwrap_or_default();
+    history.insert(*target_masp_epoch, replaced_precision);
+    ctx.write(&history_key, history)
+}
+
+/// Write the identity conversions of the asset types of the given token at
+/// the target epoch
+fn write_target_epoch_conversions<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    summary: &mut ResetSummary,
+    token_address: &Address,
+    denomination: Denomination,
+    target_masp_epoch: &MaspEpoch,
+    digits: &[MaspDigitPos],
+) -> TxResult {
+    for_each_target_epoch_conversion(
+        token_address,
+        denomination,
+        target_masp_epoch,
+        |digit, asset_type, conversion| {
+            if !digits.contains(&digit) {
+                return Ok(());
+            }
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            write_conversion(
+                ctx,
+                summary,
+                *target_masp_epoch,
+                digit,
+                &conversion_key,
+                conversion,
+            )
+        },
+    )
+}
+
+/// Compute the identity conversions of the asset types of the given token at
+/// the target epoch, which leave notes of that epoch as they are, and pass
+/// each of them, along with the digit and asset type it applies to, to the
+/// given function
+fn for_each_target_epoch_conversion(
+    token_address: &Address,
+    denomination: Denomination,
+    target_masp_epoch: &MaspEpoch,
+    mut f: impl FnMut(MaspDigitPos, AssetType, &AllowedConversion) -> TxResult,
+) -> TxResult {
+    let conversion = AllowedConversion::from(I128Sum::zero());
+    for digit in MaspDigitPos::iter() {
+        // TOK[current_ep, digit]
+        let asset_type = encode_asset_type(
+            token_address.clone(),
+            denomination,
+            digit,
+            Some(*target_masp_epoch),
+        )
+        .map_err(Error::new)?;
+        f(digit, asset_type, &conversion)?;
+    }
+
+    Ok(())
+}
+
+/// Get the digits whose conversions are written for the given token, which
+/// are all of them unless empty digits are skipped
+fn written_digits(ctx: &impl StorageRead, token_address: &Address) -> Result<Vec<MaspDigitPos>> {
+    if !SKIP_EMPTY_DIGITS {
+        return Ok(MaspDigitPos::iter().collect());
+    }
+    let supply =
+        ctx.read::<token::Amount>(&token::storage_key::minted_balance_key(token_address))?;
+    Ok(occupied_digits(supply))
+}
+
+/// Get the digits that notes of a token with the given total supply can hold
+/// an amount in. Every digit is kept if the supply is unknown.
+fn occupied_digits(supply: Option<token::Amount>) -> Vec<MaspDigitPos> {
+    match supply {
+        Some(supply) if supply <= token::Amount::from_u64(u64::MAX) => vec![MaspDigitPos::Zero],
+        _ => MaspDigitPos::iter().collect(),
+    }
+}
+
+/// Change the denomination of a token and convert the notes of every dated
+/// asset type of the old denomination into the asset type of the new
+/// denomination and the target epoch:
+/// -PRECISION OLD[ep, digit] + PRECISION NEW[target_epoch, digit]
+/// Undated asset types are left out, as the node only applies the conversions
+/// of the asset types in its conversion state, which are all dated.
+fn migrate_denomination<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    token_address: &Address,
+    old_denomination: Denomination,
+    new_denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(token_address.clone(), None);
+    let last_reset_epoch = last_reset_epoch(target_masp_epoch)?;
+    for digit in MaspDigitPos::iter() {
+        // NEW[current_ep, digit]
+        let new_asset_type = encode_asset_type(
+            token_address.clone(),
+            new_denomination,
+            digit,
+            Some(*target_masp_epoch),
+        )
+        .map_err(Error::new)?;
+        for epoch in MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_reset_epoch) {
+            // OLD[ep, digit]
+            let old_asset_type =
+                encode_asset_type(token_address.clone(), old_denomination, digit, Some(epoch))
+                    .map_err(Error::new)?;
+            let conversion = rational_conversion(old_asset_type, new_asset_type, precision, None)?;
+            let conversion_key = masp_conversion_key(target_masp_epoch, &old_asset_type);
+            write_conversion(
+                ctx,
+                &mut summary,
+                epoch,
+                digit,
+                &conversion_key,
+                &conversion,
+            )?;
+        }
+    }
+    // Store the new denomination of the token
+    summary.record_key(&token::storage_key::denom_key(token_address));
+    ctx.write(
+        &token::storage_key::denom_key(token_address),
+        new_denomination,
+    )?;
+
+    Ok(summary)
+}
+
+/// Multiply the rewards in the conversions already scheduled for the target
+/// epoch of the given token, for instance by an earlier proposal reinstating
+/// rewards, by a rational factor. Only the reward component is scaled: the
+/// amounts of TOK[ep, digit] and TOK[target_epoch, digit] that carry the
+/// principal are left untouched, so the principal-only conversions written by
+/// a reset are left as they are. Fails if the conversion of any epoch and
+/// digit is not scheduled, rather than scaling nothing.
+fn scale_token<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    token_address: &Address,
+    denomination: Denomination,
+    scale: RewardScale,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(token_address.clone(), None);
+    let last_reset_epoch = last_reset_epoch(target_masp_epoch)?;
+    for digit in MaspDigitPos::iter() {
+        // TOK[current_ep, digit]
+        let target_asset_type = encode_asset_type(
+            token_address.clone(),
+            denomination,
+            digit,
+            Some(*target_masp_epoch),
+        )
+        .map_err(Error::new)?;
+        for epoch in MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_reset_epoch) {
+            // TOK[ep, digit]
+            let asset_type =
+                encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+                    .map_err(Error::new)?;
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            let conversion = ctx
+                .read::<AllowedConversion>(&conversion_key)?
+                .ok_or_else(|| {
+                    Error::new_alloc(format!(
+                        "the rewards of token {token_address} cannot be rescaled: no conversion \
+                         of MASP epoch {epoch} and digit {digit:?} is scheduled for the target \
+                         epoch"
+                    ))
+                })?;
+            let scaled = AllowedConversion::from(scale_rewards(
+                &conversion.into(),
+                &[asset_type, target_asset_type],
+                scale,
+            )?);
+            write_conversion(ctx, &mut summary, epoch, digit, &conversion_key, &scaled)?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Change the base native precision, which doubles as the reward precision of
+/// the native token, and rewrite the native conversions scheduled for the
+/// target epoch so that they are expressed against the new precision. Every
+/// component of NAM[ep, digit]'s conversion is multiplied by NEW / OLD, where
+/// OLD is the precision in effect at the target epoch. Epochs without a
+/// scheduled conversion are skipped.
+fn rescale_base_native_precision<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    native_token: &Address,
+    denomination: Denomination,
+    precision: Precision,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(native_token.clone(), Some(precision));
+    let scheduled_base_native_precision_key =
+        masp_scheduled_base_native_precision_key(target_masp_epoch);
+    let scheduled_reward_precision_key =
+        masp_scheduled_reward_precision_key(target_masp_epoch, native_token);
+    // Prefer the precision already scheduled for the target epoch since that
+    // is the one the scheduled conversions were written with
+    let old_precision = match ctx.read::<Precision>(&scheduled_base_native_precision_key)? {
+        Some(precision) => precision,
+        None => ctx
+            .read::<Precision>(&masp_base_native_precision_key())?
+            .ok_or_else(|| Error::new_const("the base native precision is not set"))?,
+    };
+    if old_precision == precision {
+        return Ok(summary);
+    }
+    let scale = precision_scale(old_precision, precision)?;
+
+    for key in [
+        &scheduled_base_native_precision_key,
+        &scheduled_reward_precision_key,
+        &precision_history_key(native_token),
+    ] {
+        summary.record_key(key);
+    }
+    ctx.write(&scheduled_base_native_precision_key, precision)?;
+    ctx.write(&scheduled_reward_precision_key, precision)?;
+    record_replaced_precision(ctx, native_token, old_precision, target_masp_epoch)?;
+
+    let last_reset_epoch = last_reset_epoch(target_masp_epoch)?;
+    for digit in MaspDigitPos::iter() {
+        for epoch in MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_reset_epoch) {
+            // NAM[ep, digit]
+            let asset_type =
+                encode_asset_type(native_token.clone(), denomination, digit, Some(epoch))
+                    .map_err(Error::new)?;
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            let Some(conversion) = ctx.read::<AllowedConversion>(&conversion_key)? else {
+                continue;
+            };
+            let rescaled = AllowedConversion::from(rescale_conversion(&conversion.into(), scale)?);
+            write_conversion(ctx, &mut summary, epoch, digit, &conversion_key, &rescaled)?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Get the factor by which amounts expressed against the old precision must be
+/// multiplied to be expressed against the new precision
+fn precision_scale(old_precision: Precision, new_precision: Precision) -> Result<RewardScale> {
+    let to_u64 = |precision: Precision| {
+        u64::try_from(precision).map_err(|_| Error::new_const("the precision does not fit a u64"))
+    };
+    if old_precision == 0 || new_precision == 0 {
+        return Err(Error::new_const(
+            "the base native precision must not be zero",
+        ));
+    }
+    Ok((to_u64(new_precision)?, to_u64(old_precision)?))
+}
+
+/// Multiply every component of the given conversion by the rational factor.
+/// Unlike [`scale_rewards`], the principal is scaled too, so every scaled
+/// amount must be exact lest the conversion stop being balanced.
+fn rescale_conversion(
+    conversion: &I128Sum,
+    (numerator, denominator): RewardScale,
+) -> Result<I128Sum> {
+    let mut rescaled = I128Sum::zero();
+    for (asset_type, amount) in conversion.components() {
+        let scaled = amount
+            .checked_mul(i128::from(numerator))
+            .ok_or_else(|| Error::new_const("rescaled conversion overflowed"))?;
+        if scaled % i128::from(denominator) != 0 {
+            return Err(Error::new_const(
+                "the conversion cannot be rescaled to the new precision exactly",
+            ));
+        }
+        rescaled += I128Sum::from_pair(*asset_type, scaled / i128::from(denominator));
+    }
+    Ok(rescaled)
+}
+
+/// Get the reward precision of the given token in effect at the target epoch,
+/// which is the one scheduled for the target epoch if any and the live one
+/// otherwise. This is the precision that the conversions scheduled for the
+/// target epoch are written with, whereas the live precision may still be the
+/// one from before the migration.
+fn target_precision(
+    ctx: &impl StorageRead,
+    token_address: &Address,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<Precision> {
+    let scheduled_key = masp_scheduled_reward_precision_key(target_masp_epoch, token_address);
+    match ctx.read::<Precision>(&scheduled_key)? {
+        Some(precision) => Ok(precision),
+        None => ctx
+            .read::<Precision>(&masp_reward_precision_key(token_address))?
+            .ok_or_else(|| {
+                Error::new_alloc(format!("token {token_address} has no reward precision"))
+            }),
+    }
+}
+
+/// Get the amount of native tokens granted per MASP epoch for every PRECISION
+/// units of a token with the given precision according to the given reward
+/// specification
+fn epoch_reward(
+    ctx: &impl StorageRead,
+    precision: Precision,
+    reward: &RewardSpec,
+) -> Result<EpochReward> {
+    let basis_points = match reward {
+        RewardSpec::PerEpoch(reward) => return Ok(*reward),
+        RewardSpec::AprBasisPoints(basis_points) => *basis_points,
+    };
+    let epoch_duration: parameters::EpochDuration = ctx
+        .read(&parameters_storage::get_epoch_duration_storage_key())?
+        .ok_or_else(|| Error::new_const("the epoch duration is not set"))?;
+    let masp_epoch_multiplier: u64 = ctx
+        .read(&parameters_storage::get_masp_epoch_multiplier_key())?
+        .ok_or_else(|| Error::new_const("the MASP epoch multiplier is not set"))?;
+    let masp_epoch_secs = epoch_duration
+        .min_duration
+        .0
+        .checked_mul(masp_epoch_multiplier)
+        .ok_or_else(|| Error::new_const("the MASP epoch duration overflowed"))?;
+    apr_epoch_reward(precision, basis_points, masp_epoch_secs)
+}
+
+/// Convert an annualized rate in basis points into the amount granted per
+/// MASP epoch of the given duration for every PRECISION units of a token:
+/// PRECISION * RATE * EPOCH_SECONDS / (10_000 * YEAR_SECONDS)
+fn apr_epoch_reward(
+    precision: Precision,
+    basis_points: u64,
+    masp_epoch_secs: u64,
+) -> Result<EpochReward> {
+    const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+    const BASIS_POINTS: u128 = 10_000;
+    precision
+        .checked_mul(u128::from(basis_points))
+        .and_then(|reward| reward.checked_mul(u128::from(masp_epoch_secs)))
+        .map(|reward| reward / (BASIS_POINTS * SECONDS_PER_YEAR))
+        .ok_or_else(|| Error::new_const("the epoch reward overflowed"))
+}
+
+/// Grant the given native token reward for every epoch of the window on top
+/// of the reset conversions scheduled for the target epoch of the given token.
+/// The conversion of TOK[ep, digit] receives the rewards of the epochs of the
+/// window from ep onwards, paid in NAM[target_epoch, digit] since the native
+/// token conversions carry no further rewards after a reset:
+/// -PRECISION TOK[ep, digit] + PRECISION TOK[target_epoch, digit]
+///     + REWARDS NAM[target_epoch, digit]
+/// Every conversion is computed from scratch and overwrites the scheduled one,
+/// rather than adding the rewards to whatever is stored, so that running the
+/// reinstatement again, for instance in the reconciliation mode, grants the
+/// rewards only once. The token is given as its address, denomination, and
+/// precision at the target epoch, and the native token as its address and
+/// denomination.
+fn reinstate_token<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    (token_address, denomination, precision): (&Address, Denomination, Precision),
+    (native_token, native_denomination): (&Address, Denomination),
+    window: EpochWindow,
+    reward: EpochReward,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<ResetSummary> {
+    let mut summary = ResetSummary::new(token_address.clone(), None);
+    let (window_start, window_end) = reset_epochs(Some(window), target_masp_epoch)?;
+    let reward = i128::try_from(reward)
+        .map_err(|_| Error::new_const("the reinstated reward does not fit into an i128"))?;
+    for digit in MaspDigitPos::iter() {
+        // TOK[current_ep, digit]
+        let target_asset_type = encode_asset_type(
+            token_address.clone(),
+            denomination,
+            digit,
+            Some(*target_masp_epoch),
+        )
+        .map_err(Error::new)?;
+        // NAM[current_ep, digit]
+        let native_asset_type = encode_asset_type(
+            native_token.clone(),
+            native_denomination,
+            digit,
+            Some(*target_masp_epoch),
+        )
+        .map_err(Error::new)?;
+        // The rewards of the window epochs from ep onwards
+        let mut accumulated: i128 = 0;
+        for epoch in MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), window_end).rev() {
+            if epoch >= window_start {
+                accumulated = accumulated
+                    .checked_add(reward)
+                    .ok_or_else(|| Error::new_const("reinstated reward overflowed"))?;
+            }
+            // TOK[ep, digit]
+            let asset_type =
+                encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+                    .map_err(Error::new)?;
+            let conversion_key = masp_conversion_key(target_masp_epoch, &asset_type);
+            if !ctx.has_key(&conversion_key)? {
+                return Err(Error::new_const(
+                    "no scheduled conversion to reinstate rewards into",
+                ));
+            }
+            let reinstated = rational_conversion(asset_type, target_asset_type, precision, None)?
+                + AllowedConversion::from(I128Sum::from_pair(native_asset_type, accumulated));
+            write_conversion(
+                ctx,
+                &mut summary,
+                epoch,
+                digit,
+                &conversion_key,
+                &reinstated,
+            )?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Build the conversion that moves PRECISION units of a token from one asset
+/// type to another and, optionally, grants a reward of NUMERATOR / DENOMINATOR
+/// units of the reward asset type for every unit of the token, rounded down:
+/// -PRECISION FROM + PRECISION TO + PRECISION * NUMERATOR / DENOMINATOR REWARD
+fn rational_conversion(
+    from: AssetType,
+    to: AssetType,
+    precision: Precision,
+    reward: Option<(AssetType, RewardScale)>,
+) -> Result<AllowedConversion> {
+    let precision = i128::try_from(precision)
+        .map_err(|_| Error::new_const("the precision does not fit into an i128"))?;
+    let mut conversion = -I128Sum::from_pair(from, precision) + I128Sum::from_pair(to, precision);
+    if let Some((reward_asset_type, (numerator, denominator))) = reward {
+        if denominator == 0 {
+            return Err(Error::new_const("the reward denominator is zero"));
+        }
+        let reward = precision
+            .checked_mul(i128::from(numerator))
+            .ok_or_else(|| Error::new_const("the conversion reward overflowed"))?
+            / i128::from(denominator);
+        conversion += I128Sum::from_pair(reward_asset_type, reward);
+    }
+    Ok(conversion.into())
+}
+
+/// Multiply every component of the given conversion, except for those of the
+/// given principal asset types, by the rational factor. Scaled amounts are
+/// rounded towards zero so that rewards are never increased by rounding.
+fn scale_rewards(
+    conversion: &I128Sum,
+    principal: &[AssetType],
+    (numerator, denominator): RewardScale,
+) -> Result<I128Sum> {
+    if denominator == 0 {
+        return Err(Error::new_const("the reward scale denominator is zero"));
+    }
+    let mut scaled = I128Sum::zero();
+    for (asset_type, amount) in conversion.components() {
+        let amount = if principal.contains(asset_type) {
+            *amount
+        } else {
+            amount
+                .checked_mul(i128::from(numerator))
+                .ok_or_else(|| Error::new_const("scaled reward overflowed"))?
+                / i128::from(denominator)
+        };
+        scaled += I128Sum::from_pair(*asset_type, amount);
+    }
+    Ok(scaled)
+}
+
+/// Construct the MASP epoch with the given number
+fn masp_epoch(epoch: u64) -> Result<MaspEpoch> {
+    MaspEpoch::try_from_epoch(Epoch(epoch), 1).map_err(Error::new_const)
+}
+
+/// Get the range of MASP epochs whose conversions are reset, which is either
+/// the given window or every epoch before the target epoch. Fails if the
+/// window is not ordered or does not precede the target epoch.
+fn reset_epochs(window: Option<EpochWindow>, target_masp_epoch: &MaspEpoch) -> Result<ResetEpochs> {
+    let last_reset_epoch = last_reset_epoch(target_masp_epoch)?;
+    let Some((window_start, window_end)) = window else {
+        return Ok((MaspEpoch::zero(), last_reset_epoch));
+    };
+    let window_start = masp_epoch(window_start)?;
+    let window_end = masp_epoch(window_end)?;
+    if window_start > window_end || window_end > last_reset_epoch {
+        return Err(Error::new_const(
+            "the reset epoch window must be ordered and precede the target epoch",
+        ));
+    }
+    Ok((window_start, window_end))
+}
+
+/// Narrow the given range of reset epochs down to the given batch, given as a
+/// number of epochs per batch and the index of the batch. Fails if the batch
+/// is empty or lies beyond the range.
+fn batch_epochs(
+    (batch_size, batch_index): (u64, u64),
+    (first_reset_epoch, last_reset_epoch): &ResetEpochs,
+) -> Result<ResetEpochs> {
+    if batch_size == 0 {
+        return Err(Error::new_const("the reset batch size must not be zero"));
+    }
+    let skipped = batch_size
+        .checked_mul(batch_index)
+        .and_then(|skipped| usize::try_from(skipped).ok())
+        .ok_or_else(|| Error::new_const("the reset batch start overflowed"))?;
+    let batch_size = usize::try_from(batch_size).unwrap_or(usize::MAX);
+    let mut batch =
+        MaspEpoch::iter_bounds_inclusive(*first_reset_epoch, *last_reset_epoch).skip(skipped);
+    let batch_start = batch
+        .next()
+        .ok_or_else(|| Error::new_const("the reset batch lies beyond the reset epochs"))?;
+    let batch_end = batch.take(batch_size - 1).last().unwrap_or(batch_start);
+    Ok((batch_start, batch_end))
+}
+
+/// Narrow the given range of reset epochs down to the given window of a token,
+/// or get `None` if they do not overlap, as when a batch lies entirely before
+/// the token was onboarded. Fails if the window is not ordered or does not
+/// precede the target epoch.
+fn token_reset_epochs(
+    window: EpochWindow,
+    (first_reset_epoch, last_reset_epoch): &ResetEpochs,
+    target_masp_epoch: &MaspEpoch,
+) -> Result<Option<ResetEpochs>> {
+    let (window_start, window_end) = reset_epochs(Some(window), target_masp_epoch)?;
+    let first_token_epoch = window_start.max(*first_reset_epoch);
+    let last_token_epoch = window_end.min(*last_reset_epoch);
+    Ok((first_token_epoch <= last_token_epoch).then_some((first_token_epoch, last_token_epoch)))
+}
+
+/// Get the key holding the batches of conversions written so far by every
+/// batched migration, keyed by the target MASP epoch of the migration
+pub fn migration_batches_key() -> storage::Key {
+    storage::Key::from(address::MASP.to_db_key())
+        .push(&MIGRATION_BATCHES_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Record that the given batch of the migration to the target epoch has been
+/// written, so that follow-up proposals can tell which batches remain. Fails
+/// if the batch has already been written.
+fn record_batch<S: StorageRead + StorageWrite>(
+    ctx: &mut S,
+    batch: (u64, u64),
+    target_masp_epoch: &MaspEpoch,
+) -> TxResult {
+    let batches_key = migration_batches_key();
+    let mut batches: BTreeMap<MaspEpoch, BTreeSet<(u64, u64)>> =
+        ctx.read(&batches_key)?.unwrap_or_default();
+    if !batches.entry(*target_masp_epoch).or_default().insert(batch) {
+        return Err(Error::new_const("the reset batch has already been written"));
+    }
+    ctx.write(&batches_key, batches)
+}
+
+/// Get the last MASP epoch whose conversions are reset, i.e. the one right
+/// before the target epoch. Fails if the target epoch is zero since there would
+/// be no epochs to reset.
+fn last_reset_epoch(target_masp_epoch: &MaspEpoch) -> Result<MaspEpoch> {
+    target_masp_epoch
+        .prev()
+        .ok_or_else(|| Error::new_const("the target MASP epoch must be greater than zero"))
+}
+
+/// Get the MASP epoch following the given one. Fails if the given epoch is the
+/// maximum representable MASP epoch.
+fn next_epoch(epoch: MaspEpoch) -> Result<MaspEpoch> {
+    epoch
+        .next()
+        .ok_or_else(|| Error::new_const("the MASP epoch has no successor"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn native_token() -> Address {
+        Address::from_str(NATIVE_TOKEN_BECH32M).unwrap()
+    }
+
+    /// Get the conversion of NAM[epoch, digit] computed by the reset routine
+    /// of the native token
+    fn native_reset_conversion(
+        native_token: &Address,
+        denomination: Denomination,
+        precision: Precision,
+        epoch: MaspEpoch,
+        target_masp_epoch: MaspEpoch,
+        digit: MaspDigitPos,
+    ) -> AllowedConversion {
+        let mut reset_conversion = None;
+        for_each_native_reset_conversion(
+            native_token,
+            denomination,
+            precision,
+            &target_masp_epoch,
+            &(epoch, epoch),
+            |_epoch, conversion_digit, _asset_type, conversion| {
+                if conversion_digit == digit {
+                    reset_conversion = Some(conversion.clone());
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+        reset_conversion.unwrap()
+    }
+
+    /// Initialize the host environment of the node with the chain ID that the
+    /// migration was built for
+    fn init_host_env() {
+        use namada_tests::tx::tx_host_env;
+
+        tx_host_env::init();
+        tx_host_env::with(|env| {
+            env.state.in_mem_mut().chain_id = chain::ChainId(CHAIN_ID.to_owned())
+        });
+    }
+
+    /// An IBC token of the registry shared by the proposals, along with its
+    /// mainnet address
+    #[derive(serde::Deserialize)]
+    struct RegistryToken {
+        channel: String,
+        base: String,
+        address: String,
+    }
+
+    /// Get the native token address and the IBC tokens, along with their
+    /// addresses, expected on the chain with the given ID, if it is a
+    /// configured network
+    fn expected_addresses(chain_id: &str) -> Option<(AddressBech32m, Vec<RegistryToken>)> {
+        match chain_id {
+            "namada.5f5de2dd1b88cba30586420" => Some((
+                test_utils::NATIVE_TOKEN,
+                serde_json::from_str(include_str!("../../token-registry.json")).unwrap(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Check that the native token and the IBC tokens have the addresses they
+    /// have on the configured chain, so that a build for one network cannot
+    /// carry the constants of another, and that an upstream change to the
+    /// hashing of IBC denominations cannot silently redirect the migration to
+    /// tokens that hold no notes
+    #[test]
+    fn test_addresses_match_chain_id() {
+        let (native_address, registry) = expected_addresses(CHAIN_ID)
+            .unwrap_or_else(|| panic!("no addresses are expected on {CHAIN_ID}"));
+        assert_eq!(
+            NATIVE_TOKEN_BECH32M, native_address,
+            "the native token is not the one of {CHAIN_ID}"
+        );
+        for token in &registry {
+            let ibc_denom = format!("transfer/{}/{}", token.channel, token.base);
+            assert_eq!(
+                ibc::ibc_token(&ibc_denom).clone(),
+                Address::decode(&token.address).unwrap(),
+                "{ibc_denom} does not derive {} on {CHAIN_ID}",
+                token.address,
+            );
+        }
+        // Every IBC token of the table must be pinned by the registry
+        for (token_address, _, _) in &TOKENS {
+            if let TokenAddress::Ibc(channel_id, base_token) = token_address {
+                let pinned = registry
+                    .iter()
+                    .find(|token| token.channel == *channel_id && token.base == *base_token)
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "transfer/{channel_id}/{base_token} has no pinned address on \
+                             {CHAIN_ID}"
+                        )
+                    });
+                assert_eq!(
+                    token_address.to_address(),
+                    Address::decode(&pinned.address).unwrap(),
+                    "transfer/{channel_id}/{base_token} does not derive {} on {CHAIN_ID}",
+                    pinned.address,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_native_reset_conversion_is_principal_only() {
+        let native_token = native_token();
+        let target = masp_epoch(10).unwrap();
+        let conversion: I128Sum = native_reset_conversion(
+            &native_token,
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+            masp_epoch(3).unwrap(),
+            target,
+            MaspDigitPos::Zero,
+        )
+        .into();
+        let old = encode_asset_type(
+            native_token.clone(),
+            NATIVE_TOKEN_DENOMINATION,
+            MaspDigitPos::Zero,
+            Some(masp_epoch(3).unwrap()),
+        )
+        .unwrap();
+        let new = encode_asset_type(
+            native_token,
+            NATIVE_TOKEN_DENOMINATION,
+            MaspDigitPos::Zero,
+            Some(target),
+        )
+        .unwrap();
+        let precision = i128::try_from(NATIVE_TOKEN_PRECISION).unwrap();
+        assert_eq!(
+            conversion,
+            -I128Sum::from_pair(old, precision) + I128Sum::from_pair(new, precision)
+        );
+    }
+
+    #[test]
+    fn test_native_reset_conversion_matches_accumulated_deltas() {
+        let native_token = native_token();
+        let target = masp_epoch(5).unwrap();
+        let digit = MaspDigitPos::Two;
+        let precision = i128::try_from(NATIVE_TOKEN_PRECISION).unwrap();
+        let asset_type = |epoch| {
+            encode_asset_type(
+                native_token.clone(),
+                NATIVE_TOKEN_DENOMINATION,
+                digit,
+                Some(epoch),
+            )
+            .unwrap()
+        };
+        // Accumulate the per-epoch deltas the same way as the non-native path
+        let mut reward = I128Sum::zero();
+        for epoch in
+            MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), target.prev().unwrap()).rev()
+        {
+            reward += -I128Sum::from_pair(asset_type(epoch), precision)
+                + I128Sum::from_pair(asset_type(epoch.next().unwrap()), precision);
+            let conversion: I128Sum = native_reset_conversion(
+                &native_token,
+                NATIVE_TOKEN_DENOMINATION,
+                NATIVE_TOKEN_PRECISION,
+                epoch,
+                target,
+                digit,
+            )
+            .into();
+            assert_eq!(conversion, reward);
+        }
+    }
+
+    #[test]
+    fn test_reset_conversions_match_reference() {
+        let target = masp_epoch(6).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let mut count = 0;
+        for_each_reset_conversion(
+            &osmo,
+            Denomination(0u8),
+            100_000_000,
+            &target,
+            &reset_epochs,
+            |epoch, digit, _asset_type, conversion| {
+                count += 1;
+                check_reset_conversion(
+                    &osmo,
+                    Denomination(0u8),
+                    100_000_000,
+                    epoch,
+                    target,
+                    digit,
+                    conversion,
+                )
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 6 * MaspDigitPos::iter().count());
+
+        // The native token goes through its own reset routine
+        let native_token = native_token();
+        let mut count = 0;
+        for_each_native_reset_conversion(
+            &native_token,
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+            &target,
+            &reset_epochs,
+            |epoch, digit, _asset_type, conversion| {
+                count += 1;
+                check_reset_conversion(
+                    &native_token,
+                    NATIVE_TOKEN_DENOMINATION,
+                    NATIVE_TOKEN_PRECISION,
+                    epoch,
+                    target,
+                    digit,
+                    conversion,
+                )
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 6 * MaspDigitPos::iter().count());
+    }
+
+    /// Check that the conversions are streamed digit by digit in descending
+    /// epochs, each accumulating the delta of its own epoch onto the one
+    /// streamed just before it
+    #[test]
+    fn test_reset_conversions_are_streamed_downwards() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(5).unwrap();
+        let mut streamed = Vec::new();
+        for_each_reset_conversion(
+            &osmo,
+            Denomination(0u8),
+            100,
+            &target,
+            &(masp_epoch(1).unwrap(), masp_epoch(3).unwrap()),
+            |epoch, digit, asset_type, conversion| {
+                assert_eq!(
+                    asset_type,
+                    encode_asset_type(osmo.clone(), Denomination(0u8), digit, Some(epoch)).unwrap()
+                );
+                assert_eq!(
+                    *conversion,
+                    closed_form_conversion(&osmo, 100, epoch, target, digit)
+                );
+                streamed.push((digit, epoch));
+                Ok(())
+            },
+        )
+        .unwrap();
+        let expected: Vec<_> = MaspDigitPos::iter()
+            .flat_map(|digit| [3, 2, 1].map(|epoch| (digit, masp_epoch(epoch).unwrap())))
+            .collect();
+        assert_eq!(streamed, expected);
+    }
+
+    /// Check that the asset types are encoded in batches of bounded size, in
+    /// the order of the given epochs
+    #[test]
+    fn test_asset_types_are_encoded_in_batches() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let last_epoch = masp_epoch(ASSET_TYPE_BATCH as u64 + 9).unwrap();
+        let mut batch_sizes = Vec::new();
+        let mut streamed = Vec::new();
+        for_each_asset_type_batch(
+            &osmo,
+            Denomination(0u8),
+            MaspDigitPos::Two,
+            MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_epoch).rev(),
+            |epochs, asset_types| {
+                assert_eq!(epochs.len(), asset_types.len());
+                batch_sizes.push(epochs.len());
+                streamed.extend(epochs.iter().copied().zip(asset_types.iter().copied()));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(batch_sizes, vec![ASSET_TYPE_BATCH, 10]);
+        let expected: Vec<_> = MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_epoch)
+            .rev()
+            .map(|epoch| {
+                let asset_type = encode_asset_type(
+                    osmo.clone(),
+                    Denomination(0u8),
+                    MaspDigitPos::Two,
+                    Some(epoch),
+                )
+                .unwrap();
+                (epoch, asset_type)
+            })
+            .collect();
+        assert_eq!(streamed, expected);
+    }
+
+    /// Get the closed form -PRECISION TOK[ep, digit] + PRECISION TOK[target, digit]
+    /// of the conversion of TOK[ep, digit]
+    fn closed_form_conversion(
+        token_address: &Address,
+        precision: Precision,
+        epoch: MaspEpoch,
+        target: MaspEpoch,
+        digit: MaspDigitPos,
+    ) -> AllowedConversion {
+        let tok = |epoch| {
+            encode_asset_type(token_address.clone(), Denomination(0u8), digit, Some(epoch)).unwrap()
+        };
+        let precision = i128::try_from(precision).unwrap();
+        (-I128Sum::from_pair(tok(epoch), precision) + I128Sum::from_pair(tok(target), precision))
+            .into()
+    }
+
+    #[test]
+    fn test_compute_conversions_of_every_epoch() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(5).unwrap();
+        let conversions = compute_conversions(
+            &osmo,
+            Denomination(0u8),
+            100,
+            &target,
+            &reset_epochs(None, &target).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(conversions.len(), 5 * MaspDigitPos::iter().count());
+        for ((epoch, digit), (asset_type, conversion)) in conversions {
+            assert_eq!(
+                asset_type,
+                encode_asset_type(osmo.clone(), Denomination(0u8), digit, Some(epoch)).unwrap()
+            );
+            assert_eq!(
+                conversion,
+                closed_form_conversion(&osmo, 100, epoch, target, digit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_conversions_of_window_before_target() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(5).unwrap();
+        // The epochs between the window and the target contribute the
+        // starting reward of the accumulation
+        let conversions = compute_conversions(
+            &osmo,
+            Denomination(0u8),
+            100,
+            &target,
+            &reset_epochs(Some((1, 2)), &target).unwrap(),
+        )
+        .unwrap();
+        let epochs: BTreeSet<_> = conversions.keys().map(|(epoch, _)| *epoch).collect();
+        assert_eq!(
+            epochs,
+            BTreeSet::from([masp_epoch(1).unwrap(), masp_epoch(2).unwrap()])
+        );
+        for ((epoch, digit), (_, conversion)) in conversions {
+            assert_eq!(
+                conversion,
+                closed_form_conversion(&osmo, 100, epoch, target, digit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_conversions_of_epoch_before_target() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(5).unwrap();
+        // The accumulation starts from no reward, leaving a single delta
+        let conversions = compute_conversions(
+            &osmo,
+            Denomination(0u8),
+            100,
+            &target,
+            &reset_epochs(Some((4, 4)), &target).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(conversions.len(), MaspDigitPos::iter().count());
+        for ((epoch, digit), (_, conversion)) in conversions {
+            assert_eq!(epoch, masp_epoch(4).unwrap());
+            assert_eq!(
+                conversion,
+                closed_form_conversion(&osmo, 100, epoch, target, digit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_conversions_rejects_unsigned_precision() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(5).unwrap();
+        let err = compute_conversions(
+            &osmo,
+            Denomination(0u8),
+            Precision::MAX,
+            &target,
+            &reset_epochs(None, &target).unwrap(),
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the reward precision does not fit into an i128"));
+    }
+
+    #[test]
+    fn test_reset_epochs_of_target_epoch_zero() {
+        // No epoch precedes the first one, so there is nothing to reset
+        let target = MaspEpoch::zero();
+        assert!(last_reset_epoch(&target).is_err());
+        assert!(reset_epochs(None, &target).is_err());
+        assert!(reset_epochs(Some((0, 0)), &target).is_err());
+    }
+
+    #[test]
+    fn test_compute_conversions_of_target_epoch_one() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(1).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        assert_eq!(reset_epochs, (MaspEpoch::zero(), MaspEpoch::zero()));
+        let conversions =
+            compute_conversions(&osmo, Denomination(0u8), 100, &target, &reset_epochs).unwrap();
+        assert_eq!(conversions.len(), MaspDigitPos::iter().count());
+        for ((epoch, digit), (_, conversion)) in conversions {
+            assert_eq!(epoch, MaspEpoch::zero());
+            assert_eq!(
+                conversion,
+                closed_form_conversion(&osmo, 100, epoch, target, digit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_conversions_of_max_target_epoch() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(u64::MAX).unwrap();
+        assert!(next_epoch(target).is_err());
+        assert_eq!(
+            last_reset_epoch(&target).unwrap(),
+            masp_epoch(u64::MAX - 1).unwrap()
+        );
+        // Only a window of epochs can be reset this close to the maximum
+        for window in [(u64::MAX - 2, u64::MAX - 1), (u64::MAX - 3, u64::MAX - 2)] {
+            let conversions = compute_conversions(
+                &osmo,
+                Denomination(0u8),
+                100,
+                &target,
+                &reset_epochs(Some(window), &target).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(conversions.len(), 2 * MaspDigitPos::iter().count());
+            for ((epoch, digit), (_, conversion)) in conversions {
+                assert_eq!(
+                    conversion,
+                    closed_form_conversion(&osmo, 100, epoch, target, digit)
+                );
+            }
+        }
+        assert!(reset_epochs(Some((u64::MAX - 1, u64::MAX)), &target).is_err());
+    }
+
+    #[test]
+    fn test_compute_conversions_past_max_epoch_fail() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        // Resetting the maximum epoch itself requires a following epoch
+        let max = masp_epoch(u64::MAX).unwrap();
+        assert!(compute_conversions(&osmo, Denomination(0u8), 100, &max, &(max, max)).is_err());
+    }
+
+    #[test]
+    fn test_scale_rewards_leaves_principal_untouched() {
+        let native_token = native_token();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let asset_type = |token: &Address, epoch| {
+            encode_asset_type(
+                token.clone(),
+                Denomination(0u8),
+                MaspDigitPos::Zero,
+                Some(masp_epoch(epoch).unwrap()),
+            )
+            .unwrap()
+        };
+        let principal = [asset_type(&osmo, 2), asset_type(&osmo, 4)];
+        let conversion = -I128Sum::from_pair(principal[0], 1_000)
+            + I128Sum::from_pair(principal[1], 1_000)
+            + I128Sum::from_pair(asset_type(&native_token, 4), 15);
+
+        let scaled = scale_rewards(&conversion, &principal, (1, 2)).unwrap();
+        assert_eq!(
+            scaled,
+            -I128Sum::from_pair(principal[0], 1_000)
+                + I128Sum::from_pair(principal[1], 1_000)
+                + I128Sum::from_pair(asset_type(&native_token, 4), 7)
+        );
+        assert!(scale_rewards(&conversion, &principal, (1, 0)).is_err());
+    }
+
+    /// Check that the rewards of every scheduled conversion are scaled, and
+    /// that a missing conversion fails the rescale rather than being skipped
+    #[test]
+    fn test_scale_token() {
+        let native_token = native_token();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let denomination = Denomination(0u8);
+        let target = masp_epoch(3).unwrap();
+        let asset_type = |token: &Address, digit, epoch| {
+            encode_asset_type(
+                token.clone(),
+                denomination,
+                digit,
+                Some(masp_epoch(epoch).unwrap()),
+            )
+            .unwrap()
+        };
+        let conversion = |digit, epoch, reward| {
+            -I128Sum::from_pair(asset_type(&osmo, digit, epoch), 1_000)
+                + I128Sum::from_pair(asset_type(&osmo, digit, 3), 1_000)
+                + I128Sum::from_pair(asset_type(&native_token, digit, 3), reward)
+        };
+        let mut ctx = test_utils::MockCtx::default();
+        for digit in MaspDigitPos::iter() {
+            for epoch in 0..3 {
+                let key = masp_conversion_key(&target, &asset_type(&osmo, digit, epoch));
+                ctx.init(&key, AllowedConversion::from(conversion(digit, epoch, 10)));
+            }
+        }
+
+        let summary = scale_token(&mut ctx, &osmo, denomination, (1, 2), &target).unwrap();
+        assert_eq!(summary.conversions.len(), 3 * MaspDigitPos::iter().count());
+        for digit in MaspDigitPos::iter() {
+            for epoch in 0..3 {
+                let key = masp_conversion_key(&target, &asset_type(&osmo, digit, epoch));
+                assert_eq!(
+                    ctx.read::<AllowedConversion>(&key).unwrap(),
+                    Some(AllowedConversion::from(conversion(digit, epoch, 5)))
+                );
+            }
+        }
+
+        let missing_key = masp_conversion_key(&target, &asset_type(&osmo, MaspDigitPos::Three, 1));
+        ctx.delete(&missing_key).unwrap();
+        let error = scale_token(&mut ctx, &osmo, denomination, (1, 2), &target).unwrap_err();
+        assert!(error.to_string().contains("cannot be rescaled"));
+    }
+
+    #[test]
+    fn test_conversion_matrix_covers_every_epoch_and_digit() {
+        let target = masp_epoch(4).unwrap();
+        let tokens: Vec<_> = TOKENS
+            .iter()
+            .map(|(token_address, denomination, precision)| {
+                (token_address.to_address(), *denomination, *precision)
+            })
+            .collect();
+        let native = (
+            native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+        );
+        let matrix = conversion_matrix(
+            &tokens,
+            &native,
+            &target,
+            &reset_epochs(None, &target).unwrap(),
+        )
+        .unwrap();
+        let target_epoch = usize::from(WRITE_TARGET_EPOCH_CONVERSION);
+        assert_eq!(
+            matrix.len(),
+            (tokens.len() + 1) * (4 + target_epoch) * MaspDigitPos::iter().count()
+        );
+    }
+
+    /// Check that planning the conversions of a precision that does not fit
+    /// into a conversion amount fails rather than panics
+    #[test]
+    fn test_conversion_matrix_rejects_unsigned_precision() {
+        let target = masp_epoch(4).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let native = (
+            native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+        );
+        let tokens = [(osmo, Denomination(6), Precision::MAX)];
+        assert!(conversion_matrix(&tokens, &native, &target, &reset_epochs).is_err());
+
+        let native = (native_token(), NATIVE_TOKEN_DENOMINATION, Precision::MAX);
+        assert!(conversion_matrix(&[], &native, &target, &reset_epochs).is_err());
+    }
+
+    #[test]
+    fn test_checked_add_sums_reports_overflow() {
+        let asset_type = |epoch| {
+            encode_asset_type(
+                native_token(),
+                NATIVE_TOKEN_DENOMINATION,
+                MaspDigitPos::Zero,
+                Some(masp_epoch(epoch).unwrap()),
+            )
+            .unwrap()
+        };
+        let lhs = I128Sum::from_pair(asset_type(0), i128::MAX - 1);
+        let rhs = I128Sum::from_pair(asset_type(0), 1) + I128Sum::from_pair(asset_type(1), 5);
+        assert_eq!(
+            checked_add_sums(&lhs, &rhs),
+            Some(
+                I128Sum::from_pair(asset_type(0), i128::MAX) + I128Sum::from_pair(asset_type(1), 5)
+            )
+        );
+        assert_eq!(checked_add_sums(&lhs, &(rhs.clone() + rhs)), None);
+    }
+
+    #[test]
+    fn test_windowed_reset_matches_full_reset() {
+        let target = masp_epoch(8).unwrap();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let collect = |reset_epochs: &ResetEpochs| {
+            let mut conversions = BTreeMap::new();
+            for_each_reset_conversion(
+                &osmo,
+                Denomination(0u8),
+                100_000_000,
+                &target,
+                reset_epochs,
+                |epoch, digit, _asset_type, conversion| {
+                    conversions.insert((epoch, digit), conversion.clone());
+                    Ok(())
+                },
+            )
+            .unwrap();
+            conversions
+        };
+        let full = collect(&reset_epochs(None, &target).unwrap());
+        let window = collect(&reset_epochs(Some((2, 5)), &target).unwrap());
+        assert_eq!(window.len(), 4 * MaspDigitPos::iter().count());
+        for (key, conversion) in window {
+            assert_eq!(full[&key], conversion);
+        }
+        assert!(reset_epochs(Some((5, 2)), &target).is_err());
+        assert!(reset_epochs(Some((2, 8)), &target).is_err());
+    }
+
+    #[test]
+    fn test_pair_precisions_preserves_reward_ratio() {
+        let atom = TokenAddress::Ibc("channel-2", "uatom");
+        let statom = TokenAddress::Ibc("channel-0", "stuatom");
+        let mut tokens = vec![
+            (atom.to_address(), Denomination(0u8), 10_000_000),
+            (statom.to_address(), Denomination(0u8), 1),
+        ];
+        // stATOM earns 4/5 of the rewards of ATOM
+        pair_precisions(&mut tokens, &[(atom, statom, (4, 5))]).unwrap();
+        assert_eq!(tokens[0].2, 10_000_000);
+        assert_eq!(tokens[1].2, 12_500_000);
+
+        assert!(paired_precision(10_000_000, (0, 1)).is_err());
+        assert!(paired_precision(1, (2, 1)).is_err());
+    }
+
+    #[test]
+    fn test_apr_epoch_reward() {
+        // 5% per year over a day long MASP epoch
+        assert_eq!(
+            apr_epoch_reward(365_000_000, 500, 24 * 60 * 60).unwrap(),
+            50_000
+        );
+        // Rewards smaller than a unit are rounded down
+        assert_eq!(apr_epoch_reward(1, 500, 24 * 60 * 60).unwrap(), 0);
+        assert!(apr_epoch_reward(u128::MAX, 2, 1).is_err());
+    }
+
+    /// Check that an annualized reward is computed with the precision in
+    /// effect at the target epoch, which the reinstated conversions are
+    /// written with, rather than with the live precision it replaces
+    #[test]
+    fn test_epoch_reward_uses_precision_of_target_epoch() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(4).unwrap();
+        let (old_precision, new_precision) = (1_000_000, 100_000_000);
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(
+            &parameters_storage::get_epoch_duration_storage_key(),
+            parameters::EpochDuration {
+                min_num_of_blocks: 1,
+                min_duration: time::DurationSecs(24 * 60 * 60),
+            },
+        );
+        ctx.init(&parameters_storage::get_masp_epoch_multiplier_key(), 1u64);
+        ctx.init(&masp_reward_precision_key(&osmo), old_precision);
+        assert_eq!(
+            target_precision(&ctx, &osmo, &target).unwrap(),
+            old_precision
+        );
+        ctx.init(
+            &masp_scheduled_reward_precision_key(&target, &osmo),
+            new_precision,
+        );
+        let precision = target_precision(&ctx, &osmo, &target).unwrap();
+        assert_eq!(precision, new_precision);
+
+        let reward = epoch_reward(&ctx, precision, &RewardSpec::AprBasisPoints(500)).unwrap();
+        assert_eq!(
+            reward,
+            apr_epoch_reward(new_precision, 500, 24 * 60 * 60).unwrap()
+        );
+        assert_ne!(
+            reward,
+            apr_epoch_reward(old_precision, 500, 24 * 60 * 60).unwrap()
+        );
+        assert_eq!(
+            epoch_reward(&ctx, precision, &RewardSpec::PerEpoch(7)).unwrap(),
+            7
+        );
+    }
+
+    /// Check that a precision already in effect is not scheduled again, and
+    /// that a different precision scheduled earlier is deleted rather than
+    /// left to override it at the target epoch
+    #[test]
+    fn test_update_precision_clears_stale_scheduled_precision() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(4).unwrap();
+        let live_key = masp_reward_precision_key(&osmo);
+        let scheduled_key = masp_scheduled_reward_precision_key(&target, &osmo);
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(&live_key, 1_000u128);
+        assert_eq!(
+            update_precision(&mut ctx, &live_key, &scheduled_key, 1_000).unwrap(),
+            None
+        );
+        assert!(ctx.diff().is_empty());
+
+        ctx.init(&scheduled_key, 10u128);
+        assert_eq!(
+            update_precision(&mut ctx, &live_key, &scheduled_key, 1_000).unwrap(),
+            None
+        );
+        assert_eq!(ctx.diff(), BTreeMap::from([(scheduled_key.clone(), None)]));
+
+        // A different precision is scheduled and replaces the one in effect
+        assert_eq!(
+            update_precision(&mut ctx, &live_key, &scheduled_key, 100).unwrap(),
+            Some(1_000)
+        );
+        assert_eq!(ctx.read::<Precision>(&scheduled_key).unwrap(), Some(100));
+        assert_eq!(ctx.read::<Precision>(&live_key).unwrap(), Some(1_000));
+    }
+
+    /// Check that the migration of a denomination converts every asset type
+    /// of the old denomination, dated or not, into the asset type of the new
+    /// denomination at the target epoch, and stores the new denomination
+    #[test]
+    fn test_migrate_denomination() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let (old_denomination, new_denomination) = (Denomination(6), Denomination(8));
+        let target = masp_epoch(3).unwrap();
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(&token::storage_key::denom_key(&osmo), old_denomination);
+
+        let summary = migrate_denomination(
+            &mut ctx,
+            &osmo,
+            old_denomination,
+            new_denomination,
+            100,
+            &target,
+        )
+        .unwrap();
+        assert_eq!(summary.epochs, 3);
+        let diff = ctx.diff();
+        assert_eq!(diff.len(), 3 * MaspDigitPos::iter().count() + 1);
+        for digit in MaspDigitPos::iter() {
+            let new_asset_type =
+                encode_asset_type(osmo.clone(), new_denomination, digit, Some(target)).unwrap();
+            for epoch in 0..3 {
+                let old_asset_type = encode_asset_type(
+                    osmo.clone(),
+                    old_denomination,
+                    digit,
+                    Some(masp_epoch(epoch).unwrap()),
+                )
+                .unwrap();
+                let conversion: AllowedConversion = ctx
+                    .read(&masp_conversion_key(&target, &old_asset_type))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    I128Sum::from(conversion),
+                    -I128Sum::from_pair(old_asset_type, 100)
+                        + I128Sum::from_pair(new_asset_type, 100)
+                );
+            }
+        }
+        assert_eq!(
+            ctx.read::<Denomination>(&token::storage_key::denom_key(&osmo))
+                .unwrap(),
+            Some(new_denomination)
+        );
+    }
+
+    /// Check that the reconciliation detects the tokens whose reset is fully
+    /// applied, and that resetting a partially applied token again only
+    /// writes its missing pieces
+    #[test]
+    fn test_reconcile_partially_applied_reset() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let denomination = Denomination(6);
+        let target = masp_epoch(4).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        let mode = ApplicationMode::Scheduled;
+        let mut ctx = test_utils::MockCtx::default();
+        let token_applied = |ctx: &test_utils::MockCtx| {
+            token_reset_applied(
+                ctx,
+                &osmo,
+                denomination,
+                1_000,
+                &target,
+                &reset_epochs,
+                mode,
+            )
+            .unwrap()
+        };
+        let native_applied = |ctx: &test_utils::MockCtx| {
+            native_reset_applied(
+                ctx,
+                &native_token(),
+                NATIVE_TOKEN_DENOMINATION,
+                NATIVE_TOKEN_PRECISION,
+                &target,
+                &reset_epochs,
+                mode,
+            )
+            .unwrap()
+        };
+        assert!(!token_applied(&ctx));
+        assert!(!native_applied(&ctx));
+
+        reset_token(
+            &mut ctx,
+            &osmo,
+            denomination,
+            1_000,
+            &target,
+            &reset_epochs,
+            mode,
+        )
+        .unwrap();
+        reset_native_token(
+            &mut ctx,
+            &native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+            &target,
+            &reset_epochs,
+            mode,
+        )
+        .unwrap();
+        assert!(token_applied(&ctx));
+        assert!(native_applied(&ctx));
+
+        // Interrupt the reset of the token after all but one conversion
+        let asset_type = encode_asset_type(
+            osmo.clone(),
+            denomination,
+            MaspDigitPos::One,
+            Some(MaspEpoch::zero()),
+        )
+        .unwrap();
+        let missing_key = masp_conversion_key(&target, &asset_type);
+        ctx.delete(&missing_key).unwrap();
+        ctx.commit();
+        assert!(!token_applied(&ctx));
+        assert!(native_applied(&ctx));
+
+        reset_token(
+            &mut ctx,
+            &osmo,
+            denomination,
+            1_000,
+            &target,
+            &reset_epochs,
+            mode,
+        )
+        .unwrap();
+        assert!(token_applied(&ctx));
+        assert_eq!(
+            ctx.diff().into_keys().collect::<Vec<_>>(),
+            vec![missing_key]
+        );
+    }
+
+    /// Check that a delisted token gets the terminal conversions of every
+    /// epoch before the target epoch and has its rewards zeroed, so that
+    /// holders can still exit while no further rewards accrue, and that every
+    /// incentive parameter the node reads at the next MASP epoch still decodes
+    #[test]
+    fn test_delist_token() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let denomination = Denomination(6);
+        let target = masp_epoch(4).unwrap();
+        let incentive_keys = [
+            token::storage_key::masp_last_inflation_key(&osmo),
+            token::storage_key::masp_last_locked_amount_key(&osmo),
+            token::storage_key::masp_max_reward_rate_key(&osmo),
+            token::storage_key::masp_locked_amount_target_key(&osmo),
+            token::storage_key::masp_kp_gain_key(&osmo),
+            token::storage_key::masp_kd_gain_key(&osmo),
+        ];
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(&incentive_keys[0], token::Amount::from(1_000u64));
+        ctx.init(&incentive_keys[1], token::Amount::from(2_000u64));
+        ctx.init(&incentive_keys[2], Dec::new(1, 1).unwrap());
+        ctx.init(&incentive_keys[3], token::Amount::from(3_000u64));
+        ctx.init(&incentive_keys[4], Dec::new(12, 1).unwrap());
+        ctx.init(&incentive_keys[5], Dec::new(12, 1).unwrap());
+
+        let summary = delist_token(&mut ctx, &osmo, denomination, 1_000_000, &target).unwrap();
+        assert_eq!(summary.epochs, 4);
+        let conversions = compute_conversions(
+            &osmo,
+            denomination,
+            1_000_000,
+            &target,
+            &reset_epochs(None, &target).unwrap(),
+        )
+        .unwrap();
+        let diff = ctx.diff();
+        assert_eq!(diff.len(), conversions.len() + 3);
+        for (asset_type, conversion) in conversions.values() {
+            let key = masp_conversion_key(&target, asset_type);
+            assert_eq!(
+                ctx.read::<AllowedConversion>(&key).unwrap().as_ref(),
+                Some(conversion)
+            );
+        }
+        // The amounts are left untouched
+        for key in [&incentive_keys[0], &incentive_keys[1], &incentive_keys[3]] {
+            assert!(!diff.contains_key(key));
+            assert!(ctx.read::<token::Amount>(key).unwrap().is_some());
+        }
+        // The reward rate and the gains are zeroed
+        for key in [&incentive_keys[2], &incentive_keys[4], &incentive_keys[5]] {
+            assert_eq!(ctx.read::<Dec>(key).unwrap(), Some(Dec::zero()));
+            assert!(summary.keys.contains(key));
+        }
+    }
+
+    /// Check that running a reinstatement again, as the reconciliation mode
+    /// does after an interrupted migration, grants the rewards only once and
+    /// writes nothing the second time
+    #[test]
+    fn test_reinstate_token_rerun_writes_nothing() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let denomination = Denomination(6);
+        let native = (native_token(), NATIVE_TOKEN_DENOMINATION);
+        let target = masp_epoch(6).unwrap();
+        let mut ctx = test_utils::MockCtx::default();
+        for digit in MaspDigitPos::iter() {
+            for epoch in 0..6 {
+                let asset_type = |epoch| {
+                    encode_asset_type(osmo.clone(), denomination, digit, Some(epoch)).unwrap()
+                };
+                ctx.init(
+                    &masp_conversion_key(&target, &asset_type(masp_epoch(epoch).unwrap())),
+                    rational_conversion(
+                        asset_type(masp_epoch(epoch).unwrap()),
+                        asset_type(target),
+                        100,
+                        None,
+                    )
+                    .unwrap(),
+                );
+            }
+        }
+        let reinstate = |ctx: &mut test_utils::MockCtx| {
+            reinstate_token(
+                ctx,
+                (&osmo, denomination, 100),
+                (&native.0, native.1),
+                (2, 3),
+                10,
+                &target,
+            )
+            .unwrap()
+        };
+
+        reinstate(&mut ctx);
+        let reinstated = ctx.storage().clone();
+        ctx.commit();
+        let summary = reinstate(&mut ctx);
+        // The conversions are recorded for the digest all the same
+        assert_eq!(summary.epochs, 4);
+        assert_eq!(ctx.storage(), &reinstated);
+        assert!(!ctx
+            .accesses()
+            .iter()
+            .any(|access| matches!(access, test_utils::StorageAccess::Write { .. })));
+    }
+
+    /// Check that the discovery finds every incentivized non-native token of
+    /// the MASP token map once, with the configured precision of the tokens
+    /// of the table and the stored precision of the others
+    #[test]
+    fn test_discover_tokens() {
+        let (configured, configured_denomination, configured_precision) = &TOKENS[0];
+        let configured = configured.to_address();
+        let other = ibc::ibc_token("transfer/channel-9/uother").clone();
+        let idle = ibc::ibc_token("transfer/channel-9/uidle").clone();
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(
+            &token::storage_key::masp_token_map_key(),
+            masp::TokenMap::from([
+                ("nam".to_owned(), native_token()),
+                ("configured".to_owned(), configured.clone()),
+                ("alias".to_owned(), configured.clone()),
+                ("other".to_owned(), other.clone()),
+                ("idle".to_owned(), idle.clone()),
+            ]),
+        );
+        for (token_address, denomination, max_reward_rate) in [
+            (native_token(), NATIVE_TOKEN_DENOMINATION, Dec::new(1, 1)),
+            (configured.clone(), *configured_denomination, Dec::new(1, 1)),
+            (other.clone(), Denomination(6), Dec::new(1, 1)),
+            (idle.clone(), Denomination(6), Some(Dec::zero())),
+        ] {
+            ctx.init(&token::storage_key::denom_key(&token_address), denomination);
+            ctx.init(
+                &token::storage_key::masp_max_reward_rate_key(&token_address),
+                max_reward_rate.unwrap(),
+            );
+        }
+        ctx.init(&masp_reward_precision_key(&other), 1_000u128);
+
+        assert_eq!(
+            discover_tokens(&ctx, &native_token()).unwrap(),
+            vec![
+                (configured, *configured_denomination, *configured_precision),
+                (other.clone(), Denomination(6), 1_000),
+            ]
+        );
+
+        // An incentivized token without a reward precision cannot be reset
+        ctx.delete(&masp_reward_precision_key(&other)).unwrap();
+        assert!(discover_tokens(&ctx, &native_token()).is_err());
+    }
+
+    /// Check that a reinstated token is granted the rewards of the window
+    /// epochs from every epoch onwards, paid in the native token of the target
+    /// epoch, and that the scheduled conversions after the window are left
+    /// untouched
+    #[test]
+    fn test_reinstate_token_rewards_window() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let denomination = Denomination(6);
+        // A native denomination other than the compiled one, as resolved from
+        // a specification overriding it
+        let native = (
+            native_token(),
+            Denomination(NATIVE_TOKEN_DENOMINATION.0 + 1),
+        );
+        let target = masp_epoch(6).unwrap();
+        let asset_type = |token: &Address, denomination, digit, epoch| {
+            encode_asset_type(token.clone(), denomination, digit, Some(epoch)).unwrap()
+        };
+        let conversion_key = |digit, epoch| {
+            masp_conversion_key(
+                &target,
+                &asset_type(&osmo, denomination, digit, masp_epoch(epoch).unwrap()),
+            )
+        };
+        // The principal-only conversion of a reset
+        let reset_conversion = |digit, epoch| {
+            -I128Sum::from_pair(
+                asset_type(&osmo, denomination, digit, masp_epoch(epoch).unwrap()),
+                100,
+            ) + I128Sum::from_pair(asset_type(&osmo, denomination, digit, target), 100)
+        };
+        let mut ctx = test_utils::MockCtx::default();
+        for digit in MaspDigitPos::iter() {
+            for epoch in 0..6 {
+                ctx.init(
+                    &conversion_key(digit, epoch),
+                    AllowedConversion::from(reset_conversion(digit, epoch)),
+                );
+            }
+        }
+
+        let summary = reinstate_token(
+            &mut ctx,
+            (&osmo, denomination, 100),
+            (&native.0, native.1),
+            (2, 3),
+            10,
+            &target,
+        )
+        .unwrap();
+        assert_eq!(summary.epochs, 4);
+        assert_eq!(
+            summary.epoch_range,
+            Some((MaspEpoch::zero(), masp_epoch(3).unwrap()))
+        );
+        let diff = ctx.diff();
+        assert_eq!(diff.len(), 4 * MaspDigitPos::iter().count());
+        for digit in MaspDigitPos::iter() {
+            let native_asset_type = asset_type(&native.0, native.1, digit, target);
+            // The epochs before the window receive the rewards of the whole
+            // window, those within it the rewards of its remaining epochs
+            for (epoch, reward) in [(0, 20), (1, 20), (2, 20), (3, 10)] {
+                let conversion: AllowedConversion =
+                    ctx.read(&conversion_key(digit, epoch)).unwrap().unwrap();
+                assert_eq!(
+                    I128Sum::from(conversion),
+                    reset_conversion(digit, epoch) + I128Sum::from_pair(native_asset_type, reward)
+                );
+            }
+            for epoch in 4..6 {
+                assert!(!diff.contains_key(&conversion_key(digit, epoch)));
+            }
+        }
+
+        // A window reaching the target epoch is rejected
+        let mut ctx = test_utils::MockCtx::default();
+        assert!(reinstate_token(
+            &mut ctx,
+            (&osmo, denomination, 100),
+            (&native.0, native.1),
+            (5, 6),
+            10,
+            &target,
+        )
+        .is_err());
+        // Rewards cannot be reinstated into conversions never scheduled
+        assert!(reinstate_token(
+            &mut ctx,
+            (&osmo, denomination, 100),
+            (&native.0, native.1),
+            (2, 3),
+            10,
+            &target,
+        )
+        .is_err());
+        assert!(ctx.diff().is_empty());
+    }
+
+    #[test]
+    fn test_rescale_conversion_to_new_precision() {
+        let target = masp_epoch(3).unwrap();
+        let conversion: I128Sum = native_reset_conversion(
+            &native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            1_000,
+            masp_epoch(1).unwrap(),
+            target,
+            MaspDigitPos::Zero,
+        )
+        .into();
+        let expected: I128Sum = native_reset_conversion(
+            &native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            250,
+            masp_epoch(1).unwrap(),
+            target,
+            MaspDigitPos::Zero,
+        )
+        .into();
+        let scale = precision_scale(1_000, 250).unwrap();
+        assert_eq!(rescale_conversion(&conversion, scale).unwrap(), expected);
+        // Rescaling must not truncate any amount
+        let reward = encode_asset_type(
+            native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            MaspDigitPos::One,
+            Some(target),
+        )
+        .unwrap();
+        let rewarded = conversion + I128Sum::from_pair(reward, 5);
+        assert!(rescale_conversion(&rewarded, scale).is_err());
+        assert!(precision_scale(0, 250).is_err());
+    }
+
+    #[test]
+    fn test_batch_epochs_partition_reset_epochs() {
+        let target = masp_epoch(10).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        let batches: Vec<_> = (0..4)
+            .map(|batch_index| batch_epochs((3, batch_index), &reset_epochs).unwrap())
+            .collect();
+        assert_eq!(
+            batches,
+            [(0, 2), (3, 5), (6, 8), (9, 9)]
+                .map(|(start, end)| (masp_epoch(start).unwrap(), masp_epoch(end).unwrap()))
+        );
+        assert!(batch_epochs((3, 4), &reset_epochs).is_err());
+        assert!(batch_epochs((0, 0), &reset_epochs).is_err());
+    }
+
+    #[test]
+    fn test_check_precision_rejects_truncating_precisions() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        for (_, _, precision) in &TOKENS {
+            assert!(check_precision(&osmo, *precision).is_ok());
+        }
+        assert!(check_precision(&native_token(), NATIVE_TOKEN_PRECISION).is_ok());
+        assert!(check_precision(&osmo, Precision::from(u64::MAX)).is_ok());
+        assert!(check_precision(&osmo, Precision::from(u64::MAX) + 1).is_err());
+        assert!(check_precision(&osmo, 0).is_err());
+    }
+
+    #[test]
+    fn test_rational_conversion_signs() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let asset_type = |token: &Address, epoch| {
+            encode_asset_type(
+                token.clone(),
+                Denomination(0u8),
+                MaspDigitPos::Zero,
+                Some(masp_epoch(epoch).unwrap()),
+            )
+            .unwrap()
+        };
+        let (from, to, reward) = (
+            asset_type(&osmo, 1),
+            asset_type(&osmo, 2),
+            asset_type(&native_token(), 2),
+        );
+        let conversion: I128Sum = rational_conversion(from, to, 1_000, Some((reward, (3, 200))))
+            .unwrap()
+            .into();
+        assert_eq!(
+            conversion,
+            -I128Sum::from_pair(from, 1_000)
+                + I128Sum::from_pair(to, 1_000)
+                + I128Sum::from_pair(reward, 15)
+        );
+        let principal: I128Sum = rational_conversion(from, to, 1_000, None).unwrap().into();
+        assert_eq!(
+            principal,
+            -I128Sum::from_pair(from, 1_000) + I128Sum::from_pair(to, 1_000)
+        );
+        assert!(rational_conversion(from, to, 1_000, Some((reward, (1, 0)))).is_err());
+    }
+
+    #[test]
+    fn test_occupied_digits_follow_supply() {
+        let all_digits: Vec<_> = MaspDigitPos::iter().collect();
+        assert_eq!(occupied_digits(None), all_digits);
+        assert_eq!(
+            occupied_digits(Some(token::Amount::from_u64(u64::MAX))),
+            [MaspDigitPos::Zero]
+        );
+        let supply = token::Amount::from_u64(u64::MAX)
+            .checked_add(token::Amount::from_u64(1))
+            .unwrap();
+        assert_eq!(occupied_digits(Some(supply)), all_digits);
+    }
+
+    /// Check that the sample asset types cover every configured token and are
+    /// encoded into their expected identifiers
+    #[test]
+    fn test_asset_type_samples() {
+        check_asset_type_samples(&ASSET_TYPE_SAMPLES).unwrap();
+        let sampled: BTreeSet<_> = ASSET_TYPE_SAMPLES
+            .iter()
+            .map(|(token_address, ..)| token_address.to_address())
+            .collect();
+        for (token_address, _, _) in TOKENS {
+            assert!(sampled.contains(&token_address.to_address()));
+        }
+        assert!(sampled.contains(&native_token()));
+        // A mismatching sample is rejected
+        let (TokenAddress::Address(address), denomination, epoch, digit, _) = ASSET_TYPE_SAMPLES[0]
+        else {
+            panic!("the samples are given by address");
+        };
+        let expected = ASSET_TYPE_SAMPLES[1].4;
+        let sample = (
+            TokenAddress::Address(address),
+            denomination,
+            epoch,
+            digit,
+            expected,
+        );
+        assert!(check_asset_type_samples(&[sample]).is_err());
+    }
+
+    /// Print the sample asset types of every configured token in the format
+    /// of `ASSET_TYPE_SAMPLES`
+    #[test]
+    #[ignore]
+    fn print_asset_type_samples() {
+        let mut tokens: Vec<_> = TOKENS
+            .iter()
+            .map(|(token_address, denomination, _)| (token_address.to_address(), *denomination))
+            .collect();
+        tokens.push((native_token(), NATIVE_TOKEN_DENOMINATION));
+        for (token_address, denomination) in tokens {
+            for (epoch, digit) in [(0, MaspDigitPos::Zero), (1, MaspDigitPos::Three)] {
+                let asset_type = encode_asset_type(
+                    token_address.clone(),
+                    denomination,
+                    digit,
+                    Some(masp_epoch(epoch).unwrap()),
+                )
+                .unwrap();
+                println!(
+                    "(TokenAddress::Address(\"{token_address}\"), Denomination({}u8), {epoch}, \
+                     MaspDigitPos::{digit:?}, \"{asset_type}\"),",
+                    denomination.0,
+                );
+            }
+        }
+    }
+
+    /// Check the asset types and the reset conversions against the vectors
+    /// computed by the pinned version of the Namada SDK, which are exported
+    /// with `cargo run -p sdk-vectors` and refreshed along with the SDK pin
+    #[test]
+    fn test_sdk_conformance() {
+        let vectors: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/vectors/sdk.json")).unwrap(),
+        )
+        .unwrap();
+        let digit = |vector: &serde_json::Value| {
+            MaspDigitPos::iter()
+                .find(|digit| u64::from(*digit as u8) == vector["digit"].as_u64().unwrap())
+                .unwrap()
+        };
+        let token = |vector: &serde_json::Value| {
+            Address::decode(vector["token"].as_str().unwrap()).unwrap()
+        };
+        let denomination = |vector: &serde_json::Value| {
+            Denomination(vector["denomination"].as_u64().unwrap() as u8)
+        };
+        let epoch_at = |vector: &serde_json::Value, field: &str| {
+            masp_epoch(vector[field].as_u64().unwrap()).unwrap()
+        };
+
+        let asset_types = vectors["asset_types"].as_array().unwrap();
+        assert!(!asset_types.is_empty());
+        for vector in asset_types {
+            let asset_type = encode_asset_type(
+                token(vector),
+                denomination(vector),
+                digit(vector),
+                Some(epoch_at(vector, "epoch")),
+            )
+            .unwrap();
+            assert_eq!(
+                asset_type.to_string(),
+                vector["asset_type"].as_str().unwrap(),
+                "the asset type of {vector} differs from the SDK"
+            );
+        }
+
+        let conversions = vectors["conversions"].as_array().unwrap();
+        assert!(!conversions.is_empty());
+        for vector in conversions {
+            let epoch = epoch_at(vector, "epoch");
+            let target = epoch_at(vector, "target_epoch");
+            let planned = compute_conversions(
+                &token(vector),
+                denomination(vector),
+                vector["precision"].as_str().unwrap().parse().unwrap(),
+                &target,
+                &(epoch, last_reset_epoch(&target).unwrap()),
+            )
+            .unwrap();
+            let (_, conversion) = &planned[&(epoch, digit(vector))];
+            assert_eq!(
+                hex::encode(borsh::to_vec(conversion).unwrap()),
+                vector["conversion"].as_str().unwrap(),
+                "the conversion of {vector} differs from the SDK"
+            );
+        }
+    }
+
+    /// Reset the given token on an empty storage, through the native routine
+    /// or the routine of the other tokens, and get the written keys
+    fn reset_keys(token_address: &Address, native: bool) -> BTreeSet<storage::Key> {
+        let mut ctx = test_utils::MockCtx::default();
+        let target = masp_epoch(4).unwrap();
+        let reset_epochs = reset_epochs(None, &target).unwrap();
+        if native {
+            reset_native_token(
+                &mut ctx,
+                token_address,
+                NATIVE_TOKEN_DENOMINATION,
+                NATIVE_TOKEN_PRECISION,
+                &target,
+                &reset_epochs,
+                ApplicationMode::Scheduled,
+            )
+            .unwrap();
+        } else {
+            reset_token(
+                &mut ctx,
+                token_address,
+                Denomination(0u8),
+                100_000_000,
+                &target,
+                &reset_epochs,
+                ApplicationMode::Scheduled,
+            )
+            .unwrap();
+        }
+        ctx.diff().into_keys().collect()
+    }
+
+    #[test]
+    fn test_base_native_precision_is_written_with_native_token() {
+        let target = masp_epoch(4).unwrap();
+        let keys = reset_keys(&native_token(), true);
+        assert!(keys.contains(&masp_scheduled_base_native_precision_key(&target)));
+        assert!(!keys.contains(&masp_base_native_precision_key()));
+    }
+
+    #[test]
+    fn test_base_native_precision_is_not_written_without_native_token() {
+        let target = masp_epoch(4).unwrap();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let keys = reset_keys(&osmo, false);
+        assert!(!keys.contains(&masp_scheduled_base_native_precision_key(&target)));
+        assert!(!keys.contains(&masp_base_native_precision_key()));
+    }
+
+    #[test]
+    fn test_base_native_precision_is_written_once() {
+        let mut ctx = test_utils::MockCtx::default();
+        apply(&mut ctx).unwrap();
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        let base_native_precision_writes = ctx
+            .accesses()
+            .into_iter()
+            .filter(|access| {
+                matches!(
+                    access,
+                    test_utils::StorageAccess::Write { key, .. }
+                        if *key == masp_scheduled_base_native_precision_key(&target)
+                )
+            })
+            .count();
+        // Only the native reset and the rescaling of the base native precision
+        // write the key
+        let expected_writes = if RESCALED_BASE_NATIVE_PRECISION.is_some() {
+            2
+        } else {
+            1
+        };
+        assert_eq!(base_native_precision_writes, expected_writes);
+        assert_eq!(
+            ctx.read::<Precision>(&masp_scheduled_base_native_precision_key(&target))
+                .unwrap(),
+            Some(RESCALED_BASE_NATIVE_PRECISION.unwrap_or(NATIVE_TOKEN_PRECISION))
+        );
+    }
+
+    /// Check that the conversions already stored with their planned value are
+    /// not written again, while the others are
+    #[test]
+    fn test_unchanged_conversions_are_not_written() {
+        // Plan the conversions over an empty storage
+        let mut planned = test_utils::MockCtx::default();
+        apply(&mut planned).unwrap();
+        let mut conversions = planned.diff().into_iter().filter_map(|(key, value)| {
+            namada_shielded_token::storage_key::is_masp_conversion_key(&key)?;
+            let conversion: AllowedConversion = borsh::from_slice(&value?).unwrap();
+            Some((key, conversion))
+        });
+        let (unchanged_key, unchanged) = conversions.next().unwrap();
+        let (changed_key, changed) = conversions
+            .find(|(_, conversion)| *conversion != unchanged)
+            .unwrap();
+
+        // Store the planned conversion under one key and a stale one under
+        // the other
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.init(&unchanged_key, unchanged.clone());
+        ctx.init(&changed_key, unchanged.clone());
+        apply(&mut ctx).unwrap();
+        let writes: Vec<_> = ctx
+            .accesses()
+            .into_iter()
+            .filter_map(|access| match access {
+                test_utils::StorageAccess::Write { key, .. } => Some(key),
+                _ => None,
+            })
+            .collect();
+        assert!(!writes.contains(&unchanged_key));
+        assert!(writes.contains(&changed_key));
+        assert_eq!(
+            ctx.read::<AllowedConversion>(&unchanged_key).unwrap(),
+            Some(unchanged)
+        );
+        assert_eq!(
+            ctx.read::<AllowedConversion>(&changed_key).unwrap(),
+            Some(changed)
+        );
+    }
+
+    /// Check the identifiers of a sample of asset types against the golden
+    /// file, so that a change to the encoding of asset types or to the
+    /// handling of denominations shows up in review
+    #[test]
+    fn test_asset_type_golden() {
+        let mut tokens: Vec<_> = TOKENS
+            .iter()
+            .map(|(token_address, denomination, _)| (token_address.to_address(), *denomination))
+            .collect();
+        tokens.push((native_token(), NATIVE_TOKEN_DENOMINATION));
+        // The denomination is part of the encoding
+        tokens.push((
+            ibc::ibc_token("transfer/channel-1/uosmo").clone(),
+            Denomination(6u8),
+        ));
+        let mut golden = String::new();
+        for (token_address, denomination) in tokens {
+            for epoch in [None, Some(0), Some(1), Some(210)] {
+                for digit in MaspDigitPos::iter() {
+                    let asset_type = encode_asset_type(
+                        token_address.clone(),
+                        denomination,
+                        digit,
+                        epoch.map(|epoch| masp_epoch(epoch).unwrap()),
+                    )
+                    .unwrap();
+                    let epoch =
+                        epoch.map_or_else(|| "undated".to_owned(), |epoch| epoch.to_string());
+                    golden.push_str(&format!(
+                        "{token_address} {} {epoch} {digit:?} {}\n",
+                        denomination.0,
+                        hex::encode(asset_type.get_identifier()),
+                    ));
+                }
+            }
+        }
+        test_utils::assert_golden(
+            &golden,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/asset_types.golden"),
+        );
+    }
+
+    /// Pin the exact storage keys of the scheduled reward precisions and
+    /// base native precision for a sample of epochs and tokens, so that an
+    /// upstream change to the key schema is caught here rather than by values
+    /// that the protocol never applies
+    #[test]
+    fn test_scheduled_key_layout_golden() {
+        let tokens = [
+            native_token(),
+            ibc::ibc_token("transfer/channel-1/uosmo").clone(),
+        ];
+        let mut golden = String::new();
+        for epoch in [0, 1, 211, u64::MAX] {
+            let epoch = masp_epoch(epoch).unwrap();
+            for token_address in &tokens {
+                golden.push_str(&format!(
+                    "{}\n",
+                    masp_scheduled_reward_precision_key(&epoch, token_address)
+                ));
+            }
+            golden.push_str(&format!(
+                "{}\n",
+                masp_scheduled_base_native_precision_key(&epoch)
+            ));
+        }
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/snapshots/scheduled_keys.golden"
+        );
+        test_utils::assert_golden(&golden, path);
+    }
+
+    #[test]
+    fn test_apply_snapshot() {
+        let mut ctx = test_utils::MockCtx::default();
+        let summaries = apply(&mut ctx).unwrap();
+        // The conversions are covered by the digest stored in the audit key
+        // rather than listed one by one
+        let mut diff = ctx.diff();
+        diff.retain(|key, _| {
+            !summaries
+                .iter()
+                .any(|summary| summary.conversions.contains_key(key))
+        });
+        test_utils::assert_snapshot(
+            &diff,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/apply.snap"),
+        );
+    }
+
+    /// Apply the migration on top of the state that the earlier proposals
+    /// left on mainnet, and check that it leaves the live state alone,
+    /// schedules the table precisions, and attributes every existing value it
+    /// overwrites to a token. The fixture is replayed and kept up to date by
+    /// the cross-phase tests, see fixtures/README.md.
+    #[test]
+    fn test_apply_over_mainnet_state() {
+        let initial = test_utils::read_state_export(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/mainnet.state"
+        ))
+        .unwrap();
+        let mut ctx = test_utils::MockCtx::new(native_token(), initial.clone());
+        let summaries = apply(&mut ctx).unwrap();
+        assert!(!summaries.is_empty());
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for (token_address, _, precision) in &TOKENS {
+            let token_address = token_address.to_address();
+            let live_precision_key = masp_reward_precision_key(&token_address);
+            assert_eq!(
+                ctx.read_bytes(&live_precision_key).unwrap(),
+                initial.get(&live_precision_key).cloned(),
+                "the live precision of {token_address} changed"
+            );
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &token_address);
+            assert_eq!(
+                ctx.read::<Precision>(&scheduled_precision_key).unwrap(),
+                Some(*precision)
+            );
+        }
+        let base_native_precision_key = masp_base_native_precision_key();
+        assert_eq!(
+            ctx.read_bytes(&base_native_precision_key).unwrap(),
+            initial.get(&base_native_precision_key).cloned()
+        );
+
+        for key in ctx.diff().keys() {
+            if !initial.contains_key(key)
+                || *key == migration_audit_key()
+                || *key == migration_config_key()
+            {
+                continue;
+            }
+            assert!(
+                summaries.iter().any(|summary| summary.keys.contains(key)),
+                "the overwritten key {key} is not attributed to any token"
+            );
+        }
+    }
+
+    /// Check the planned writes against the conversions computed from first
+    /// principles by [`reference_reset_conversion`] and the precisions of the
+    /// token table, rather than against another run of the migration
+    #[test]
+    fn test_planned_writes_match_reference() {
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        let (first_reset_epoch, last_reset_epoch) = reset_epochs(None, &target).unwrap();
+        let native = (
+            native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+        );
+        let tokens = TOKENS
+            .iter()
+            .map(|(token_address, denomination, precision)| {
+                (token_address.to_address(), *denomination, *precision)
+            })
+            .chain([native.clone()]);
+
+        let mut expected = BTreeMap::new();
+        for (token_address, denomination, precision) in tokens {
+            for epoch in MaspEpoch::iter_bounds_inclusive(first_reset_epoch, last_reset_epoch) {
+                for digit in MaspDigitPos::iter() {
+                    let conversion = reference_reset_conversion(
+                        &token_address,
+                        denomination,
+                        precision,
+                        epoch,
+                        target,
+                        digit,
+                    )
+                    .unwrap()
+                    .into_iter()
+                    .fold(I128Sum::zero(), |sum, (asset_type, amount)| {
+                        sum + I128Sum::from_pair(asset_type, amount)
+                    });
+                    let asset_type =
+                        encode_asset_type(token_address.clone(), denomination, digit, Some(epoch))
+                            .unwrap();
+                    expected.insert(
+                        masp_conversion_key(&target, &asset_type),
+                        borsh::to_vec(&AllowedConversion::from(conversion)).unwrap(),
+                    );
+                }
+            }
+            expected.insert(
+                masp_scheduled_reward_precision_key(&target, &token_address),
+                borsh::to_vec(&precision).unwrap(),
+            );
+        }
+        expected.insert(
+            masp_scheduled_base_native_precision_key(&target),
+            borsh::to_vec(&native.2).unwrap(),
+        );
+
+        // Set the bookkeeping of the migration apart
+        let planned: BTreeMap<_, _> = planned_writes()
+            .unwrap()
+            .into_iter()
+            .filter(|(key, _)| *key != migration_config_key() && *key != migration_audit_key())
+            .collect();
+        assert_eq!(planned, expected);
+    }
+
+    #[test]
+    fn test_shadow_redirects_every_write() {
+        let mut ctx = test_utils::MockCtx::default();
+        apply(&mut ctx).unwrap();
+        let real_writes = ctx.diff();
+
+        let mut ctx = test_utils::MockCtx::default();
+        apply(&mut shadow::Shadow::new(&mut ctx)).unwrap();
+        let shadow_writes = ctx.diff();
+        assert_eq!(
+            shadow_writes,
+            real_writes
+                .into_iter()
+                .map(|(key, value)| (shadow::shadow_key(&key), Some(value.unwrap_or_default())))
+                .collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    /// Check that a dry run plans exactly the writes of the migration and
+    /// leaves the storage untouched
+    #[test]
+    fn test_dry_run_commits_nothing() {
+        let mut ctx = test_utils::MockCtx::default();
+        apply(&mut ctx).unwrap();
+        let real_writes = ctx.diff();
+
+        let ctx = test_utils::MockCtx::default();
+        let mut dry_run = dry_run::DryRun::new(&ctx);
+        apply(&mut dry_run).unwrap();
+        assert_eq!(dry_run.writes(), &real_writes);
+        assert!(dry_run.log().len() >= real_writes.len());
+        assert!(ctx.diff().is_empty());
+    }
+
+    /// Check that planning and applying the migration twice produce
+    /// byte-identical outputs, so that no iteration order of a hash map or
+    /// randomness brought in by a dependency makes the writes differ between
+    /// the validators executing the proposal
+    #[test]
+    fn test_repeated_execution_is_deterministic() {
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        let tokens: Vec<_> = TOKENS
+            .iter()
+            .map(|(token_address, denomination, precision)| {
+                (token_address.to_address(), *denomination, *precision)
+            })
+            .collect();
+        let native = (
+            native_token(),
+            NATIVE_TOKEN_DENOMINATION,
+            NATIVE_TOKEN_PRECISION,
+        );
+        let plan = || {
+            let matrix = conversion_matrix(
+                &tokens,
+                &native,
+                &target,
+                &reset_epochs(None, &target).unwrap(),
+            )
+            .unwrap();
+            borsh::to_vec(&matrix).unwrap()
+        };
+        assert_eq!(plan(), plan());
+
+        // Apply over cloned states holding a live precision to overwrite
+        let mut ctx = test_utils::MockCtx::default();
+        let (osmo, _, _) = &tokens[0];
+        let live_precision: Precision = 1_000;
+        ctx.init(&masp_reward_precision_key(osmo), live_precision);
+        ctx.commit();
+        let mut first = ctx.clone();
+        let mut second = ctx;
+        let first_log = {
+            let mut log = audit::AuditLog::new(&mut first);
+            apply(&mut log).unwrap();
+            log.0.into_entries()
+        };
+        let second_log = {
+            let mut log = audit::AuditLog::new(&mut second);
+            apply(&mut log).unwrap();
+            log.0.into_entries()
+        };
+        assert_eq!(first_log, second_log);
+        assert_eq!(first.accesses(), second.accesses());
+        assert_eq!(first.diff(), second.diff());
+
+        // Execute the transaction twice under the host environment
+        let execute = || {
+            use namada_tests::tx::ctx;
+
+            init_host_env();
+            let mut tx = Tx::from_type(transaction::TxType::Raw);
+            tx.push_default_inner_tx();
+            execute(ctx(), tx.batch_first_tx()).unwrap();
+            first
+                .diff()
+                .keys()
+                .map(|key| (key.clone(), ctx().read_bytes(key).unwrap()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(execute(), execute());
+    }
+
+    #[test]
+    fn test_audit_log_records_every_write() {
+        let mut ctx = test_utils::MockCtx::default();
+        let mut log = audit::AuditLog::new(&mut ctx);
+        apply(&mut log).unwrap();
+        let entries = log.0.into_entries();
+
+        // The last write of every key is the value left in storage
+        let mut last_writes = BTreeMap::new();
+        for entry in &entries {
+            last_writes.insert(entry.key.clone(), entry.value_hash);
+        }
+        let diff = ctx.diff();
+        assert_eq!(
+            last_writes.keys().collect::<Vec<_>>(),
+            diff.keys().collect::<Vec<_>>()
+        );
+        for (key, value) in diff {
+            assert_eq!(last_writes[&key], value.map(hash::Hash::sha256));
+        }
+        assert_eq!(audit::render(&entries).lines().count(), entries.len());
+    }
+
+    /// Execute the transaction under the host environment of the node, so
+    /// that missing host functions or gas accounting problems surface before
+    /// the proposal is submitted
+    #[test]
+    fn test_execute_in_host_env() {
+        use namada_tests::tx::ctx;
+
+        init_host_env();
+        let mut tx = Tx::from_type(transaction::TxType::Raw);
+        tx.push_default_inner_tx();
+        execute(ctx(), tx.batch_first_tx()).unwrap();
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for (token_address, _, precision) in &TOKENS {
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &token_address.to_address());
+            assert_eq!(
+                ctx().read::<Precision>(&scheduled_precision_key).unwrap(),
+                Some(*precision)
+            );
+        }
+        let digests: MigrationDigests = ctx().read(&migration_audit_key()).unwrap().unwrap();
+        assert!(digests.contains_key(&(target, RESET_BATCH)));
+    }
+
+    /// Check that the stored audit digest is the digest of every conversion
+    /// stored for the target epoch after the migration, keyed by its storage
+    /// key and hashed from its stored value
+    #[test]
+    fn test_audit_digest_matches_written_conversions() {
+        use sha2::{Digest, Sha256};
+
+        let mut ctx = test_utils::MockCtx::default();
+        let config = Config::resolve(&ctx, None).unwrap();
+        apply_config(&mut ctx, &config).unwrap();
+
+        let conversions: BTreeMap<storage::Key, [u8; 32]> = ctx
+            .storage()
+            .iter()
+            .filter(|(key, _)| {
+                namada_shielded_token::storage_key::is_masp_conversion_key(key)
+                    .is_some_and(|(epoch, _)| epoch == config.target_epoch)
+            })
+            .map(|(key, value)| (key.clone(), Sha256::digest(value).into()))
+            .collect();
+        assert!(!conversions.is_empty());
+        let digests: MigrationDigests = ctx.read(&migration_audit_key()).unwrap().unwrap();
+        assert_eq!(
+            digests[&(config.target_epoch, RESET_BATCH)],
+            digest_conversions(&conversions)
+        );
+    }
+
+    /// Check that the digest of every batch of a migration survives the
+    /// batches written after it for the same target epoch
+    #[test]
+    fn test_batch_digests_survive_later_batches() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let target = masp_epoch(8).unwrap();
+        let mut ctx = test_utils::MockCtx::default();
+        let mut batch_digests = Vec::new();
+        for batch in [(4, 0), (4, 1)] {
+            record_batch(&mut ctx, batch, &target).unwrap();
+            let reset_epochs = batch_epochs(batch, &reset_epochs(None, &target).unwrap()).unwrap();
+            let mut summary = ResetSummary::new(osmo.clone(), Some(100));
+            for_each_reset_conversion(
+                &osmo,
+                Denomination(6),
+                100,
+                &target,
+                &reset_epochs,
+                |epoch, digit, asset_type, conversion| {
+                    let key = masp_conversion_key(&target, &asset_type);
+                    write_conversion(&mut ctx, &mut summary, epoch, digit, &key, conversion)
+                },
+            )
+            .unwrap();
+            batch_digests.push((batch, summary.digest()));
+            write_migration_digest(&mut ctx, &[summary], &target, Some(batch)).unwrap();
+        }
+
+        let digests: MigrationDigests = ctx.read(&migration_audit_key()).unwrap().unwrap();
+        assert_eq!(digests.len(), 2);
+        assert_ne!(batch_digests[0].1, batch_digests[1].1);
+        for (batch, digest) in batch_digests {
+            assert_eq!(digests[&(target, Some(batch))], digest);
+        }
+    }
+
+    /// Check that the configuration applied by the migration is stored under
+    /// its target epoch, next to those of earlier migrations
+    #[test]
+    fn test_apply_stores_config() {
+        let mut ctx = test_utils::MockCtx::default();
+        let earlier_target = masp_epoch(100).unwrap();
+        let earlier_config = Config {
+            target_epoch: earlier_target,
+            ..Config::resolve(&ctx, None).unwrap()
+        };
+        ctx.init(
+            &migration_config_key(),
+            BTreeMap::from([(earlier_target, earlier_config.clone())]),
+        );
+        let config = Config::resolve(&ctx, None).unwrap();
+        apply_config(&mut ctx, &config).unwrap();
+
+        let configs: BTreeMap<MaspEpoch, Config> =
+            ctx.read(&migration_config_key()).unwrap().unwrap();
+        assert_eq!(
+            configs,
+            BTreeMap::from([
+                (earlier_target, earlier_config),
+                (config.target_epoch, config)
+            ])
+        );
+    }
+
+    /// Check that a configuration resetting the native token as a regular
+    /// token is rejected before writing anything
+    #[test]
+    fn test_apply_native_token_in_token_table_fails() {
+        let mut ctx = test_utils::MockCtx::default();
+        let mut config = Config::resolve(&ctx, None).unwrap();
+        config.tokens.push((
+            config.native_token.clone(),
+            config.native_denomination,
+            config.native_precision,
+        ));
+        let err = apply_config(&mut ctx, &config).unwrap_err();
+        assert!(err.to_string().contains("must not be in the token table"));
+        assert!(ctx.diff().is_empty());
+    }
+
+    /// Check that the summary of every token reset is emitted as a conversion
+    /// reset event carrying the token, the numbers of epochs and conversions
+    /// written, their digest, and the scheduled precision
+    #[test]
+    fn test_summaries_are_emitted_as_events() {
+        use namada_events::{Event, EventLevel};
+
+        let mut ctx = test_utils::MockCtx::default();
+        let summaries = apply_spec(&mut ctx, None).unwrap();
+        assert_eq!(summaries.len(), TOKENS.len() + 1);
+        for summary in summaries {
+            let token = summary.token.clone();
+            let epochs = summary.epochs;
+            let conversions = summary.conversions.len() as u64;
+            let digest = summary.digest();
+            let precision = summary.precision;
+            assert!(epochs > 0 && conversions > 0);
+
+            let event = Event::from(summary);
+            assert_eq!(*event.kind(), events::CONVERSION_RESET);
+            assert_eq!(*event.level(), EventLevel::Tx);
+            assert_eq!(
+                event.read_attribute::<events::ResetToken<'_>>().unwrap(),
+                token
+            );
+            assert_eq!(
+                event.read_attribute::<events::ResetEpochs>().unwrap(),
+                epochs
+            );
+            assert_eq!(
+                event.read_attribute::<events::ResetConversions>().unwrap(),
+                conversions
+            );
+            assert_eq!(
+                event.read_attribute::<events::ResetDigest>().unwrap(),
+                digest.to_string()
+            );
+            assert_eq!(
+                event
+                    .read_attribute_opt::<events::ResetPrecision>()
+                    .unwrap(),
+                precision
+            );
+        }
+    }
+
+    /// Check that the transaction refuses to execute on a chain other than the
+    /// one of the network it was built for
+    #[test]
+    fn test_execute_on_other_chain_fails() {
+        use namada_tests::tx::{ctx, tx_host_env};
+
+        tx_host_env::init();
+        tx_host_env::with(|env| {
+            env.state.in_mem_mut().chain_id = chain::ChainId("other-chain.0".to_owned())
+        });
+        let mut tx = Tx::from_type(transaction::TxType::Raw);
+        tx.push_default_inner_tx();
+        assert!(execute(ctx(), tx.batch_first_tx()).is_err());
+        assert!(ctx().read_bytes(&migration_audit_key()).unwrap().is_none());
+
+        // The chain is checked before the data is decoded, so even a
+        // malformed specification is refused for naming the wrong chain
+        let mut tx = Tx::from_type(transaction::TxType::Raw);
+        tx.add_serialized_data(vec![SPEC_VERSION, 0xff, 0xff]);
+        let error = execute(ctx(), tx.batch_first_tx()).unwrap_err().to_string();
+        assert!(error.contains(&format!(
+            "built for {CHAIN_ID} on the {NETWORK} network, not for other-chain.0"
+        )));
+    }
+
+    /// Check that the chain ID guard accepts the chain of the selected network
+    /// and nothing else
+    #[test]
+    fn test_check_chain_id() {
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.chain_id = chain::ChainId(CHAIN_ID.to_owned());
+        check_chain_id(&ctx).unwrap();
+        for chain_id in ["", "other-chain.0", format!("{CHAIN_ID}0").as_str()] {
+            ctx.chain_id = chain::ChainId(chain_id.to_owned());
+            assert!(check_chain_id(&ctx).is_err());
+        }
+    }
+
+    /// Check that the tokens of a specification decoded from the transaction
+    /// data replace those of the compiled tables
+    #[test]
+    fn test_apply_tokens_of_tx_data() {
+        use namada_tests::tx::ctx;
+
+        let (token_address, denomination, precision) = &TOKENS[0];
+        let spec = UpgradeSpec {
+            tokens: Some(vec![TokenSpec {
+                address: Some(token_address.to_address()),
+                symbol: None,
+                denomination: Some(*denomination),
+                precision: *precision,
+                operation: None,
+                epochs: None,
+            }]),
+            ..UpgradeSpec::default()
+        };
+        init_host_env();
+        let mut tx = Tx::from_type(transaction::TxType::Raw);
+        tx.add_serialized_data(borsh::to_vec(&spec).unwrap());
+        execute(ctx(), tx.batch_first_tx()).unwrap();
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for (index, (token_address, _, _)) in TOKENS.iter().enumerate() {
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &token_address.to_address());
+            let scheduled_precision = ctx().read::<Precision>(&scheduled_precision_key).unwrap();
+            assert_eq!(scheduled_precision, (index == 0).then_some(*precision));
+        }
+    }
+
+    /// Check that a target epoch supplied with the specification replaces the
+    /// compiled one, and that epochs that are not shortly after the current
+    /// MASP epoch are rejected
+    #[test]
+    fn test_apply_target_epoch_of_spec() {
+        let mut ctx = test_utils::MockCtx::default();
+        ctx.write(&parameters_storage::get_masp_epoch_multiplier_key(), 4u64)
+            .unwrap();
+        ctx.block_epoch = Epoch(820);
+        let spec = |target_epoch| UpgradeSpec {
+            target_epoch: Some(masp_epoch(target_epoch).unwrap()),
+            ..UpgradeSpec::default()
+        };
+
+        for target_epoch in [204, 205, 205 + MAX_TARGET_EPOCH_LEAD + 1] {
+            assert!(apply_spec(&mut ctx.clone(), Some(&spec(target_epoch))).is_err());
+        }
+
+        apply_spec(&mut ctx, Some(&spec(207))).unwrap();
+        let target = masp_epoch(207).unwrap();
+        for (token_address, _, precision) in &TOKENS {
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &token_address.to_address());
+            assert_eq!(
+                ctx.read::<Precision>(&scheduled_precision_key).unwrap(),
+                Some(*precision)
+            );
+        }
+    }
+
+    /// Check that a reset window supplied with the specification limits the
+    /// epochs whose conversions are written
+    #[test]
+    fn test_apply_reset_epochs_of_spec() {
+        let mut ctx = test_utils::MockCtx::default();
+        let spec = UpgradeSpec {
+            reset_epochs: Some((200, 210)),
+            ..UpgradeSpec::default()
+        };
+        let summaries = apply_spec(&mut ctx, Some(&spec)).unwrap();
+        for summary in &summaries {
+            assert_eq!(
+                summary.epoch_range,
+                Some((masp_epoch(200).unwrap(), masp_epoch(210).unwrap()))
+            );
+        }
+    }
+
+    /// Check that a native token supplied with the specification replaces the
+    /// compiled one if it is the native token of the chain, and is rejected
+    /// otherwise
+    #[test]
+    fn test_apply_native_token_of_spec() {
+        let testnet_native_token =
+            Address::from_str("tnam1qxfj3sf6a0meahdu9t6znp05g8zx4dkjtgyn9gfu").unwrap();
+        let mut ctx = test_utils::MockCtx::new(testnet_native_token.clone(), BTreeMap::new());
+        let spec = |native_token| UpgradeSpec {
+            native_token: Some(native_token),
+            ..UpgradeSpec::default()
+        };
+
+        assert!(apply_spec(&mut ctx.clone(), Some(&spec(native_token()))).is_err());
+
+        let summaries = apply_spec(&mut ctx, Some(&spec(testnet_native_token.clone()))).unwrap();
+        assert!(summaries
+            .iter()
+            .any(|summary| summary.token == testnet_native_token));
+        assert!(summaries
+            .iter()
+            .all(|summary| summary.token != native_token()));
+    }
+
+    /// Check that a specification listing the native token among the tokens is
+    /// rejected descriptively before writing anything
+    #[test]
+    fn test_native_token_of_spec_tokens_fails() {
+        let spec = UpgradeSpec {
+            tokens: Some(vec![TokenSpec {
+                address: Some(native_token()),
+                symbol: None,
+                denomination: Some(NATIVE_TOKEN_DENOMINATION),
+                precision: NATIVE_TOKEN_PRECISION,
+                operation: None,
+                epochs: None,
+            }]),
+            ..UpgradeSpec::default()
+        };
+        let mut ctx = test_utils::MockCtx::default();
+        let err = apply_spec(&mut ctx, Some(&spec)).unwrap_err();
+        assert!(err.to_string().contains("is the native token"));
+        assert!(ctx.diff().is_empty());
+    }
+
+    /// Check that the tokens of a specification without a denomination take
+    /// the one stored on chain, and are rejected if none is stored
+    #[test]
+    fn test_apply_stored_denomination_of_spec() {
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let spec = UpgradeSpec {
+            tokens: Some(vec![TokenSpec {
+                address: Some(osmo.clone()),
+                symbol: None,
+                denomination: None,
+                precision: 100_000_000,
+                operation: None,
+                epochs: None,
+            }]),
+            ..UpgradeSpec::default()
+        };
+
+        let mut ctx = test_utils::MockCtx::default();
+        assert!(apply_spec(&mut ctx, Some(&spec)).is_err());
+
+        let mut ctx = test_utils::MockCtx::default();
+        let stored_denomination = Denomination(6);
+        ctx.write(&token::storage_key::denom_key(&osmo), stored_denomination)
+            .unwrap();
+        apply_spec(&mut ctx, Some(&spec)).unwrap();
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        let asset_type = encode_asset_type(
+            osmo,
+            stored_denomination,
+            MaspDigitPos::Zero,
+            Some(MaspEpoch::zero()),
+        )
+        .unwrap();
+        assert!(ctx
+            .read_bytes(&masp_conversion_key(&target, &asset_type))
+            .unwrap()
+            .is_some());
+    }
+
+    /// Check that the tokens excluded by the specification are left out of
+    /// the compiled table, and that excluding a token outside of it fails
+    #[test]
+    fn test_apply_excluded_tokens_of_spec() {
+        let untrn = ibc::ibc_token("transfer/channel-7/untrn").clone();
+        let spec = UpgradeSpec {
+            exclude: Some(vec![untrn.clone()]),
+            ..UpgradeSpec::default()
+        };
+        let mut ctx = test_utils::MockCtx::default();
+        let summaries = apply_spec(&mut ctx, Some(&spec)).unwrap();
+        assert!(summaries.iter().all(|summary| summary.token != untrn));
+        assert_eq!(summaries.len(), TOKENS.len());
+
+        let spec = UpgradeSpec {
+            exclude: Some(vec![native_token()]),
+            ..UpgradeSpec::default()
+        };
+        let mut ctx = test_utils::MockCtx::default();
+        assert!(apply_spec(&mut ctx, Some(&spec)).is_err());
+    }
+
+    /// Check that a specification keeping the precisions resets the
+    /// conversions with the current precision of every token and schedules no
+    /// new precision
+    #[test]
+    fn test_apply_keeping_precisions() {
+        let mut ctx = test_utils::MockCtx::default();
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let live_precision: Precision = 1_000;
+        ctx.init(&masp_reward_precision_key(&osmo), live_precision);
+        ctx.init(&masp_reward_precision_key(&native_token()), live_precision);
+        let spec = UpgradeSpec {
+            precision_mode: Some(PrecisionMode::Keep),
+            ..UpgradeSpec::default()
+        };
+        let summaries = apply_spec(&mut ctx, Some(&spec)).unwrap();
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for token_address in [&osmo, &native_token()] {
+            let summary = summaries
+                .iter()
+                .find(|summary| &summary.token == token_address)
+                .unwrap();
+            assert_eq!(summary.precision, Some(live_precision));
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, token_address);
+            assert_eq!(ctx.read_bytes(&scheduled_precision_key).unwrap(), None);
+        }
+    }
+
+    /// Check that a token given a window of epochs only has the conversions of
+    /// those epochs reset, while the other tokens have every epoch reset
+    #[test]
+    fn test_apply_token_epochs_of_spec() {
+        let mut ctx = test_utils::MockCtx::default();
+        let token_spec = |token_address: &Address, epochs| TokenSpec {
+            address: Some(token_address.clone()),
+            symbol: None,
+            denomination: Some(Denomination(6)),
+            precision: 1_000_000,
+            operation: None,
+            epochs,
+        };
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let atom = ibc::ibc_token("transfer/channel-1/uatom").clone();
+        let spec = UpgradeSpec {
+            tokens: Some(vec![
+                token_spec(&osmo, Some((100, 150))),
+                token_spec(&atom, None),
+            ]),
+            ..UpgradeSpec::default()
+        };
+        let summaries = apply_spec(&mut ctx.clone(), Some(&spec)).unwrap();
+        let summary = |token_address: &Address| {
+            summaries
+                .iter()
+                .find(|summary| &summary.token == token_address)
+                .unwrap()
+        };
+        assert_eq!(summary(&osmo).epochs, 51);
+        assert_eq!(
+            summary(&osmo).epoch_range,
+            Some((masp_epoch(100).unwrap(), masp_epoch(150).unwrap()))
+        );
+        assert_eq!(summary(&atom).epochs, 211);
+
+        // The window must precede the target epoch
+        let spec = UpgradeSpec {
+            tokens: Some(vec![token_spec(&osmo, Some((100, 211)))]),
+            ..UpgradeSpec::default()
+        };
+        assert!(apply_spec(&mut ctx, Some(&spec)).is_err());
+    }
+
+    /// Check that a single specification can reset, rescale, and prune
+    /// different tokens
+    #[test]
+    fn test_apply_operations_of_spec() {
+        let mut ctx = test_utils::MockCtx::default();
+        let token_spec = |token_address: &Address, operation| TokenSpec {
+            address: Some(token_address.clone()),
+            symbol: None,
+            denomination: Some(Denomination(6)),
+            precision: 1_000_000,
+            operation,
+            epochs: None,
+        };
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let atom = ibc::ibc_token("transfer/channel-1/uatom").clone();
+        let tia = ibc::ibc_token("transfer/channel-1/utia").clone();
+        let spec = UpgradeSpec {
+            tokens: Some(vec![
+                token_spec(&osmo, None),
+                token_spec(&atom, Some(TokenOperation::Rescale((1, 2)))),
+                token_spec(&tia, Some(TokenOperation::Prune)),
+            ]),
+            ..UpgradeSpec::default()
+        };
+        let config = Config::resolve(&ctx, Some(&spec)).unwrap();
+        assert_eq!(
+            config.tokens,
+            vec![(osmo.clone(), Denomination(6), 1_000_000)]
+        );
+        assert_eq!(
+            config.scaled_tokens,
+            vec![(atom.clone(), Denomination(6), (1, 2))]
+        );
+        assert_eq!(
+            config.delisted_tokens,
+            vec![(tia.clone(), Denomination(6), 1_000_000)]
+        );
+
+        // The rescaled token has rewards scheduled by an earlier proposal
+        let target = config.target_epoch;
+        let native_token = native_token();
+        let mut atom_conversions = Vec::new();
+        for digit in MaspDigitPos::iter() {
+            let asset_type = |token: &Address, epoch| {
+                encode_asset_type(token.clone(), Denomination(6), digit, Some(epoch)).unwrap()
+            };
+            let last_reset_epoch = last_reset_epoch(&target).unwrap();
+            for epoch in MaspEpoch::iter_bounds_inclusive(MaspEpoch::zero(), last_reset_epoch) {
+                let conversion = -I128Sum::from_pair(asset_type(&atom, epoch), 1_000_000)
+                    + I128Sum::from_pair(asset_type(&atom, target), 1_000_000)
+                    + I128Sum::from_pair(asset_type(&native_token, target), 10);
+                let key = masp_conversion_key(&target, &asset_type(&atom, epoch));
+                ctx.init(&key, AllowedConversion::from(conversion));
+                atom_conversions.push(key);
+            }
+        }
+        // The pruned token is incentivized, as the node expects of every token
+        // of the MASP token map
+        for key in [
+            token::storage_key::masp_last_inflation_key(&tia),
+            token::storage_key::masp_last_locked_amount_key(&tia),
+            token::storage_key::masp_locked_amount_target_key(&tia),
+        ] {
+            ctx.init(&key, token::Amount::from(1_000u64));
+        }
+        for key in [
+            token::storage_key::masp_max_reward_rate_key(&tia),
+            token::storage_key::masp_kp_gain_key(&tia),
+            token::storage_key::masp_kd_gain_key(&tia),
+        ] {
+            ctx.init(&key, Dec::new(1, 1).unwrap());
+        }
+
+        let summaries = apply_config(&mut ctx, &config).unwrap();
+        let summary = |token_address: &Address| {
+            summaries
+                .iter()
+                .find(|summary| &summary.token == token_address)
+                .unwrap()
+        };
+        // Only the reset token has a new precision scheduled
+        assert_eq!(summary(&osmo).precision, Some(1_000_000));
+        assert_eq!(summary(&atom).precision, None);
+        // The rescaled token has the rewards of its scheduled conversions
+        // halved
+        assert_eq!(summary(&atom).conversions.len(), atom_conversions.len());
+        // The pruned token has its notes converted and its rewards zeroed,
+        // while every incentive parameter still decodes
+        assert!(summary(&tia).epochs > 0);
+        for key in [
+            token::storage_key::masp_last_inflation_key(&tia),
+            token::storage_key::masp_last_locked_amount_key(&tia),
+            token::storage_key::masp_locked_amount_target_key(&tia),
+        ] {
+            assert_eq!(
+                ctx.read::<token::Amount>(&key).unwrap(),
+                Some(token::Amount::from(1_000u64))
+            );
+        }
+        for key in [
+            token::storage_key::masp_max_reward_rate_key(&tia),
+            token::storage_key::masp_kp_gain_key(&tia),
+            token::storage_key::masp_kd_gain_key(&tia),
+        ] {
+            assert_eq!(ctx.read::<Dec>(&key).unwrap(), Some(Dec::zero()));
+        }
+    }
+
+    /// Check that rescaling a token with no conversions scheduled for the
+    /// target epoch fails the whole migration instead of doing nothing
+    #[test]
+    fn test_rescale_without_scheduled_conversions_fails() {
+        let mut ctx = test_utils::MockCtx::default();
+        let atom = ibc::ibc_token("transfer/channel-1/uatom").clone();
+        let spec = UpgradeSpec {
+            tokens: Some(vec![TokenSpec {
+                address: Some(atom),
+                symbol: None,
+                denomination: Some(Denomination(6)),
+                precision: 1_000_000,
+                operation: Some(TokenOperation::Rescale((1, 2))),
+                epochs: None,
+            }]),
+            ..UpgradeSpec::default()
+        };
+        let config = Config::resolve(&ctx, Some(&spec)).unwrap();
+        let error = apply_config(&mut ctx, &config).unwrap_err();
+        assert!(error.to_string().contains("cannot be rescaled"));
+    }
+
+    /// Check that symbolic token references are resolved through the MASP
+    /// token map, whichever channel the token came through
+    #[test]
+    fn test_resolve_symbols_of_spec() {
+        let mut ctx = test_utils::MockCtx::default();
+        let osmo = ibc::ibc_token("transfer/channel-7/uosmo").clone();
+        let atom = ibc::ibc_token("transfer/channel-2/uatom").clone();
+        let token_map_key = token::storage_key::masp_token_map_key();
+        let mut token_map = masp::TokenMap::new();
+        token_map.insert("transfer/channel-7/uosmo".to_owned(), osmo.clone());
+        ctx.init(&token_map_key, token_map.clone());
+        let token_spec = |symbol: &str| TokenSpec {
+            address: None,
+            symbol: Some(symbol.to_owned()),
+            denomination: Some(Denomination(6)),
+            precision: 1_000_000,
+            operation: None,
+            epochs: None,
+        };
+        let spec = UpgradeSpec {
+            tokens: Some(vec![token_spec("OSMO")]),
+            ..UpgradeSpec::default()
+        };
+        let config = Config::resolve(&ctx, Some(&spec)).unwrap();
+        assert_eq!(config.tokens, vec![(osmo, Denomination(6), 1_000_000)]);
+
+        // Symbols missing from the registry or the token map are rejected
+        for symbol in ["UNKNOWN", "ATOM"] {
+            let spec = UpgradeSpec {
+                tokens: Some(vec![token_spec(symbol)]),
+                ..UpgradeSpec::default()
+            };
+            assert!(Config::resolve(&ctx, Some(&spec)).is_err());
+        }
+
+        // A symbol carried by two channels at once is ambiguous
+        token_map.insert("transfer/channel-9/uosmo".to_owned(), atom);
+        ctx.init(&token_map_key, token_map);
+        let spec = UpgradeSpec {
+            tokens: Some(vec![token_spec("OSMO")]),
+            ..UpgradeSpec::default()
+        };
+        assert!(Config::resolve(&ctx, Some(&spec)).is_err());
+    }
+
+    /// Check that a specification applying the precisions immediately writes
+    /// them to the live keys and schedules none
+    #[test]
+    fn test_apply_precisions_immediately() {
+        let mut ctx = test_utils::MockCtx::default();
+        let spec = UpgradeSpec {
+            application_mode: Some(ApplicationMode::Immediate),
+            ..UpgradeSpec::default()
+        };
+        let summaries = apply_spec(&mut ctx, Some(&spec)).unwrap();
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for summary in &summaries {
+            let precision = summary.precision.unwrap();
+            let live_precision = ctx
+                .read::<Precision>(&masp_reward_precision_key(&summary.token))
+                .unwrap();
+            assert_eq!(live_precision, Some(precision));
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &summary.token);
+            assert_eq!(ctx.read_bytes(&scheduled_precision_key).unwrap(), None);
+        }
+        assert_eq!(
+            ctx.read::<Precision>(&masp_base_native_precision_key())
+                .unwrap(),
+            Some(NATIVE_TOKEN_PRECISION)
+        );
+        assert_eq!(
+            ctx.read_bytes(&masp_scheduled_base_native_precision_key(&target))
+                .unwrap(),
+            None
+        );
+    }
+
+    /// Check that a configuration built from scratch, as by a network reusing
+    /// this crate, resets its own tokens and nothing from the compiled tables
+    #[test]
+    fn test_apply_config_of_other_network() {
+        let native_token =
+            Address::from_str("tnam1qxfj3sf6a0meahdu9t6znp05g8zx4dkjtgyn9gfu").unwrap();
+        let token = ibc::ibc_token("transfer/channel-42/uother").clone();
+        let config = Config {
+            native_token: native_token.clone(),
+            native_denomination: Denomination(6),
+            native_precision: 1_000_000,
+            target_epoch: masp_epoch(20).unwrap(),
+            reset_epochs: None,
+            tokens: vec![(token.clone(), Denomination(6), 1_000_000)],
+            token_epochs: BTreeMap::new(),
+            scaled_tokens: Vec::new(),
+            delisted_tokens: Vec::new(),
+            application_mode: ApplicationMode::Scheduled,
+        };
+        let mut ctx = test_utils::MockCtx::new(native_token.clone(), BTreeMap::new());
+        let summaries = apply_config(&mut ctx, &config).unwrap();
+
+        let reset_tokens: BTreeSet<_> = summaries.iter().map(|summary| &summary.token).collect();
+        assert_eq!(reset_tokens, BTreeSet::from([&token, &native_token]));
+        for summary in &summaries {
+            assert_eq!(summary.epochs, 20);
+        }
+    }
+
+    /// Check that the configuration resolved without a specification is the
+    /// one compiled into the WASM
+    #[test]
+    fn test_resolve_compiled_config() {
+        let ctx = test_utils::MockCtx::default();
+        let config = Config::resolve(&ctx, None).unwrap();
+        assert_eq!(config.native_token, native_token());
+        assert_eq!(config.native_precision, NATIVE_TOKEN_PRECISION);
+        assert_eq!(
+            config.target_epoch,
+            MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap()
+        );
+        assert_eq!(config.tokens.len(), TOKENS.len());
+    }
+
+    /// Check that transaction data that is not a valid specification is
+    /// rejected rather than ignored
+    #[test]
+    fn test_invalid_tx_data_fails() {
+        let decode = |data: Vec<u8>| {
+            let mut tx = Tx::from_type(transaction::TxType::Raw);
+            tx.add_serialized_data(data);
+            UpgradeSpec::from_tx_data(&tx.batch_first_tx())
+        };
+        assert!(decode(vec![0xff; 3]).is_err());
+        let osmo = TokenSpec {
+            address: Some(ibc::ibc_token("transfer/channel-1/uosmo").clone()),
+            symbol: None,
+            denomination: None,
+            precision: 100_000_000,
+            operation: None,
+            epochs: None,
+        };
+        for invalid_spec in [
+            UpgradeSpec {
+                version: SPEC_VERSION + 1,
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![osmo.clone(), osmo.clone()]),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![TokenSpec {
+                    precision: 0,
+                    ..osmo.clone()
+                }]),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                reset_epochs: Some((10, 9)),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![TokenSpec {
+                    operation: Some(TokenOperation::Rescale((1, 0))),
+                    ..osmo.clone()
+                }]),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![TokenSpec {
+                    epochs: Some((10, 9)),
+                    ..osmo.clone()
+                }]),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![TokenSpec {
+                    operation: Some(TokenOperation::Prune),
+                    epochs: Some((0, 9)),
+                    ..osmo.clone()
+                }]),
+                ..UpgradeSpec::default()
+            },
+            UpgradeSpec {
+                tokens: Some(vec![osmo.clone()]),
+                exclude: Some(vec![osmo.address.clone().unwrap()]),
+                ..UpgradeSpec::default()
+            },
+        ] {
+            assert!(decode(borsh::to_vec(&invalid_spec).unwrap()).is_err());
+        }
+
+        let mut tx = Tx::from_type(transaction::TxType::Raw);
+        tx.push_default_inner_tx();
+        assert_eq!(
+            UpgradeSpec::from_tx_data(&tx.batch_first_tx()).unwrap(),
+            None
+        );
+    }
+
+    /// Check that a specification of another version is rejected with an
+    /// error naming both versions, even if the rest of it does not decode
+    #[test]
+    fn test_spec_of_other_version_fails_descriptively() {
+        let decode_error = |data: Vec<u8>| {
+            let mut tx = Tx::from_type(transaction::TxType::Raw);
+            tx.add_serialized_data(data);
+            UpgradeSpec::from_tx_data(&tx.batch_first_tx())
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut older_spec = borsh::to_vec(&UpgradeSpec::default()).unwrap();
+        older_spec[0] = SPEC_VERSION - 1;
+        let error = decode_error(older_spec);
+        assert!(error.contains(&format!(
+            "upgrade spec v{SPEC_VERSION} required, got v{}",
+            SPEC_VERSION - 1
+        )));
+
+        // A newer version may have any layout after the version
+        let error = decode_error(vec![SPEC_VERSION + 1, 0xff, 0xff]);
+        assert!(error.contains(&format!(
+            "upgrade spec v{} is newer than the v{SPEC_VERSION}",
+            SPEC_VERSION + 1
+        )));
+    }
+
+    /// Check that a specification written as JSON by a proposal author decodes
+    /// to the same specification as its Borsh encoding in the proposal data
+    #[test]
+    fn test_spec_json_matches_borsh() {
+        let spec: UpgradeSpec = serde_json::from_str(&format!(
+            r#"{{
+                "version": {SPEC_VERSION},
+                "tokens": [
+                    {{
+                        "address": "{}",
+                        "denomination": 0,
+                        "precision": 100000000
+                    }}
+                ],
+                "exclude": null,
+                "target_epoch": null,
+                "reset_epochs": [0, 210],
+                "native_token": null,
+                "precision_mode": "Keep",
+                "application_mode": "Immediate"
+            }}"#,
+            ibc::ibc_token("transfer/channel-1/uosmo")
+        ))
+        .unwrap();
+        spec.validate().unwrap();
+        let encoded = borsh::to_vec(&spec).unwrap();
+        assert_eq!(UpgradeSpec::try_from_slice(&encoded).unwrap(), spec);
+        assert_eq!(spec.precision_mode, Some(PrecisionMode::Keep));
+        assert_eq!(spec.application_mode, Some(ApplicationMode::Immediate));
+    }
+
+    /// Check that a note shielded under the asset type of an old epoch can
+    /// still be spent at the target epoch once the scheduled conversions are
+    /// applied: converting the note with the written conversion must leave
+    /// exactly the same amount under the asset type of the target epoch, so
+    /// that a transfer of that amount balances as the MASP verifier requires
+    #[test]
+    fn test_old_notes_are_spendable_at_target_epoch() {
+        let mut ctx = test_utils::MockCtx::default();
+        apply(&mut ctx).unwrap();
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        let (first_epoch, last_epoch) = reset_epochs(None, &target).unwrap();
+        for (token_address, denomination, precision) in &TOKENS {
+            let token_address = token_address.to_address();
+            for digit in MaspDigitPos::iter() {
+                let asset_type = |epoch| {
+                    encode_asset_type(token_address.clone(), *denomination, digit, Some(epoch))
+                        .unwrap()
+                };
+                // The note of a few whole multiples of the precision that the
+                // transfer spends, and the output it creates at the target
+                let times = 7;
+                let amount = i128::try_from(*precision).unwrap() * times;
+                let output = I128Sum::from_pair(asset_type(target), amount);
+                for epoch in MaspEpoch::iter_bounds_inclusive(first_epoch, last_epoch) {
+                    let note_asset_type = asset_type(epoch);
+                    let conversion: AllowedConversion = ctx
+                        .read(&masp_conversion_key(&target, &note_asset_type))
+                        .unwrap()
+                        .unwrap_or_else(|| {
+                            panic!("no conversion of {token_address} from epoch {epoch:?}")
+                        });
+                    let conversion = I128Sum::from(conversion);
+                    // The value balance of the spend, the conversion applied
+                    // once per multiple of the precision, and the output
+                    let mut balance = I128Sum::from_pair(note_asset_type, amount);
+                    for _ in 0..times {
+                        balance = checked_add_sums(&balance, &conversion).unwrap();
+                    }
+                    assert_eq!(
+                        balance, output,
+                        "the note of {token_address} from epoch {epoch:?} cannot be spent at \
+                         the target epoch"
+                    );
+                }
+            }
+        }
+    }
+
+    proptest::proptest! {
+        /// Check that the rewards accumulated over every reset epoch telescope
+        /// into the closed form -PRECISION TOK[ep] + PRECISION TOK[target_ep]
+        #[test]
+        fn test_reset_conversions_telescope(
+            precision in 1..=Precision::from(u64::MAX),
+            target in 1u64..48,
+            denomination in 0u8..=18,
+        ) {
+            let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+            let denomination = Denomination(denomination);
+            let target = masp_epoch(target).unwrap();
+            let mut count = 0;
+            for_each_reset_conversion(
+                &osmo,
+                denomination,
+                precision,
+                &target,
+                &reset_epochs(None, &target).unwrap(),
+                |epoch, digit, _asset_type, conversion| {
+                    count += 1;
+                    check_reset_conversion(
+                        &osmo,
+                        denomination,
+                        precision,
+                        epoch,
+                        target,
+                        digit,
+                        conversion,
+                    )
+                },
+            )
+            .unwrap();
+            proptest::prop_assert!(count > 0);
+        }
+
+        /// Check that the accumulated rewards cannot overflow, even for
+        /// precisions far beyond the ones accepted by check_precision and for
+        /// target epochs at the end of the epoch range. The deltas telescope,
+        /// so no component of an accumulated reward ever exceeds the
+        /// precision in magnitude.
+        #[test]
+        fn test_reset_conversions_do_not_overflow(
+            precision in 1..=Precision::try_from(i128::MAX).unwrap(),
+            target in 9..=u64::MAX,
+            window in 1u64..8,
+        ) {
+            let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+            let target_epoch = masp_epoch(target).unwrap();
+            let reset_epochs = reset_epochs(Some((target - window, target - 1)), &target_epoch)
+                .unwrap();
+            for_each_reset_conversion(
+                &osmo,
+                Denomination(0u8),
+                precision,
+                &target_epoch,
+                &reset_epochs,
+                |_epoch, _digit, _asset_type, conversion| {
+                    let conversion: I128Sum = conversion.clone().into();
+                    for (_, amount) in conversion.components() {
+                        assert!(amount.unsigned_abs() <= precision);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    /// A step of the model of the conversion state of a token over several
+    /// governance proposals
+    #[derive(Clone, Debug)]
+    enum ModelStep {
+        /// Shield the given amount of the token in the current epoch
+        Shield(u64),
+        /// Move to the next epoch, accumulating the conversions the way the
+        /// node does at every epoch boundary
+        Advance,
+        /// Reset the conversions of every past epoch along with a change of
+        /// the precision to the given one
+        Reset(Precision),
+        /// Reset the conversions of the given number of most recent epochs,
+        /// keeping the precision
+        WindowReset(u64),
+        /// Drop the conversions of the epochs in which no note was created
+        Prune,
+    }
+
+    fn model_step() -> impl proptest::strategy::Strategy<Value = ModelStep> {
+        use proptest::prelude::*;
+
+        prop_oneof![
+            (1u64..=100).prop_map(|thousands| ModelStep::Shield(thousands * 1_000)),
+            Just(ModelStep::Advance),
+            Just(ModelStep::Advance),
+            proptest::sample::select(vec![10, 100, 1_000]).prop_map(ModelStep::Reset),
+            (1u64..4).prop_map(ModelStep::WindowReset),
+            Just(ModelStep::Prune),
+        ]
+    }
+
+    proptest::proptest! {
+        /// Apply sequences of resets, precision changes, prunes, and epoch
+        /// changes to a model of the conversion state of a token, and check
+        /// after every step that every note can be converted into the current
+        /// epoch without creating or destroying any of the token
+        #[test]
+        fn test_conversion_state_machine(
+            steps in proptest::collection::vec(model_step(), 1..40),
+        ) {
+            let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+            let digit = MaspDigitPos::Zero;
+            let tok = |epoch: u64| {
+                encode_asset_type(
+                    osmo.clone(),
+                    Denomination(0u8),
+                    digit,
+                    Some(masp_epoch(epoch).unwrap()),
+                )
+                .unwrap()
+            };
+            let delta = |epoch: u64, precision: Precision| {
+                let precision = i128::try_from(precision).unwrap();
+                -I128Sum::from_pair(tok(epoch), precision)
+                    + I128Sum::from_pair(tok(epoch + 1), precision)
+            };
+            let mut current = 0u64;
+            let mut precision: Precision = 100;
+            let mut conversions: BTreeMap<u64, I128Sum> = BTreeMap::new();
+            let mut notes: Vec<(u64, u64)> = Vec::new();
+            let reset = |conversions: &mut BTreeMap<u64, I128Sum>,
+                         precision: Precision,
+                         first_epoch: u64,
+                         current: u64| {
+                let target = masp_epoch(current).unwrap();
+                let reset_epochs =
+                    (masp_epoch(first_epoch).unwrap(), masp_epoch(current - 1).unwrap());
+                let planned = compute_conversions(
+                    &osmo,
+                    Denomination(0u8),
+                    precision,
+                    &target,
+                    &reset_epochs,
+                )
+                .unwrap();
+                for epoch in first_epoch..current {
+                    let (_, conversion) = &planned[&(masp_epoch(epoch).unwrap(), digit)];
+                    conversions.insert(epoch, conversion.clone().into());
+                }
+            };
+
+            for step in steps {
+                match step {
+                    ModelStep::Shield(amount) => notes.push((current, amount)),
+                    ModelStep::Advance => {
+                        for conversion in conversions.values_mut() {
+                            *conversion =
+                                checked_add_sums(conversion, &delta(current, precision)).unwrap();
+                        }
+                        conversions.insert(current, delta(current, precision));
+                        current += 1;
+                    }
+                    ModelStep::Reset(new_precision) if current > 0 => {
+                        precision = new_precision;
+                        reset(&mut conversions, precision, 0, current);
+                    }
+                    ModelStep::WindowReset(epochs) if current > 0 => {
+                        reset(&mut conversions, precision, current.saturating_sub(epochs), current);
+                    }
+                    ModelStep::Prune => {
+                        conversions.retain(|epoch, _| {
+                            notes.iter().any(|(note_epoch, _)| note_epoch == epoch)
+                        });
+                    }
+                    _ => {}
+                }
+
+                // Convert every note into the current epoch
+                let mut shielded = 0u128;
+                let mut converted = 0u128;
+                for (epoch, amount) in &notes {
+                    shielded += u128::from(*amount);
+                    if *epoch == current {
+                        converted += u128::from(*amount);
+                        continue;
+                    }
+                    let conversion = conversions.get(epoch);
+                    proptest::prop_assert!(
+                        conversion.is_some(),
+                        "the notes of epoch {} cannot be converted",
+                        epoch
+                    );
+                    let scale = i128::try_from(precision).unwrap();
+                    let expected = -I128Sum::from_pair(tok(*epoch), scale)
+                        + I128Sum::from_pair(tok(current), scale);
+                    proptest::prop_assert_eq!(conversion.unwrap(), &expected);
+                    let times = u128::from(*amount) / precision;
+                    proptest::prop_assert_eq!(times * precision, u128::from(*amount));
+                    converted += times * precision;
+                }
+                proptest::prop_assert_eq!(shielded, converted);
+            }
+        }
+    }
+
+    /// Check that the reset conversions match the ones the node itself
+    /// accumulates at every epoch boundary for a token without rewards, which
+    /// are exactly the conversions that a reset leaves behind
+    #[test]
+    fn test_reset_conversions_match_node_updates() {
+        use namada_shielded_token::conversion::update_allowed_conversions;
+        use namada_state::testing::TestState;
+
+        let target = 6u64;
+        let osmo = ibc::ibc_token("transfer/channel-1/uosmo").clone();
+        let native_token = native_token();
+        let precision: Precision = 100_000_000;
+
+        let mut state = TestState::default();
+        state.in_mem_mut().native_token = native_token.clone();
+        state
+            .write(&parameters_storage::get_masp_epoch_multiplier_key(), 1u64)
+            .unwrap();
+        state
+            .write(&parameters_storage::get_epochs_per_year_key(), 365u64)
+            .unwrap();
+        let token_map = masp::TokenMap::from([
+            ("nam".to_owned(), native_token.clone()),
+            ("transfer/channel-1/uosmo".to_owned(), osmo.clone()),
+        ]);
+        state
+            .write(&token::storage_key::masp_token_map_key(), token_map)
+            .unwrap();
+        state
+            .write(&masp_base_native_precision_key(), NATIVE_TOKEN_PRECISION)
+            .unwrap();
+        // Neither token earns any rewards
+        for (token_address, denomination, precision) in [
+            (
+                &native_token,
+                NATIVE_TOKEN_DENOMINATION,
+                NATIVE_TOKEN_PRECISION,
+            ),
+            (&osmo, Denomination(0u8), precision),
+        ] {
+            use token::storage_key::*;
+            state
+                .write(&denom_key(token_address), denomination)
+                .unwrap();
+            state
+                .write(&masp_reward_precision_key(token_address), precision)
+                .unwrap();
+            state
+                .write(&masp_max_reward_rate_key(token_address), Dec::zero())
+                .unwrap();
+            state
+                .write(&masp_kp_gain_key(token_address), Dec::zero())
+                .unwrap();
+            state
+                .write(&masp_kd_gain_key(token_address), Dec::zero())
+                .unwrap();
+            for key in [
+                masp_locked_amount_target_key(token_address),
+                masp_last_locked_amount_key(token_address),
+                masp_last_inflation_key(token_address),
+            ] {
+                state.write(&key, token::Amount::zero()).unwrap();
+            }
+        }
+        // Let the node accumulate its conversions up to the target epoch
+        for epoch in 0..=target {
+            state.in_mem_mut().block.epoch = Epoch(epoch);
+            update_allowed_conversions::<
+                _,
+                namada_parameters::Store<_>,
+                namada_trans_token::Store<_>,
+            >(&mut state)
+            .unwrap();
+        }
+
+        let target = masp_epoch(target).unwrap();
+        let assets = &state.in_mem().conversion_state.assets;
+        for (token_address, denomination, precision) in [
+            (&osmo, Denomination(0u8), precision),
+            (
+                &native_token,
+                NATIVE_TOKEN_DENOMINATION,
+                NATIVE_TOKEN_PRECISION,
+            ),
+        ] {
+            for_each_reset_conversion(
+                token_address,
+                denomination,
+                precision,
+                &target,
+                &reset_epochs(None, &target).unwrap(),
+                |_epoch, _digit, asset_type, conversion| {
+                    assert_eq!(&assets[&asset_type].conversion, conversion);
+                    Ok(())
+                },
+            )
+            .unwrap();
+        }
+    }
+}
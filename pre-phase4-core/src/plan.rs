@@ -0,0 +1,25 @@
+//! The write set of the migration, computed over an empty storage without a
+//! transaction context, so that audit scripts and the tools of this
+//! repository derive the expected writes from the same source of truth.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+use storage_adapters::MockCtx;
+
+/// Apply the migration to an empty storage and get every key it writes along
+/// with the Borsh encoding of the written value, in key order. Over an empty
+/// storage, the migration deletes no key.
+pub fn planned_writes() -> Result<Vec<(Key, Vec<u8>)>> {
+    let native_token = Address::from_str(crate::NATIVE_TOKEN_BECH32M)
+        .expect("unable to construct native token address");
+    let mut storage = MockCtx::new(native_token, BTreeMap::new());
+    crate::apply(&mut storage)?;
+    Ok(storage
+        .storage()
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect())
+}
@@ -0,0 +1,56 @@
+//! A storage adapter redirecting every write of the migration under the
+//! `dry-run/` prefix, so that a rehearsal proposal can be executed on a
+//! testnet and its writes inspected on chain without touching the conversion
+//! state.
+//!
+//! Reads see the redirected writes first and fall back to the real keys, so
+//! the migration behaves exactly as it would without the redirection. Deleted
+//! keys are written under the prefix as empty values, which no Borsh encoded
+//! value written by the migration can be.
+
+use namada_tx_prelude::storage::{DbKeySeg, Key};
+use namada_tx_prelude::*;
+use storage_adapters::{Adapted, Adapter};
+
+// The storage key segment prefixing every redirected write
+const SHADOW_PREFIX: &str = "dry-run";
+
+/// Get the key under which the write of the given key is redirected
+pub fn shadow_key(key: &Key) -> Key {
+    Key::from(DbKeySeg::StringSeg(SHADOW_PREFIX.to_owned())).join(key)
+}
+
+/// An adapter redirecting the writes to its storage under the `dry-run/`
+/// prefix
+pub struct Shadow<'ctx, S>(&'ctx mut S);
+
+impl<'ctx, S> Shadow<'ctx, S> {
+    /// Redirect the writes to the given storage
+    pub fn new(inner: &'ctx mut S) -> Adapted<Self> {
+        Adapted(Self(inner))
+    }
+}
+
+impl<S: StorageRead + StorageWrite> Adapter for Shadow<'_, S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        self.0
+    }
+
+    fn read_bytes(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        match self.0.read_bytes(&shadow_key(key))? {
+            Some(value) if value.is_empty() => Ok(None),
+            Some(value) => Ok(Some(value)),
+            None => self.0.read_bytes(key),
+        }
+    }
+
+    fn write_bytes(&mut self, key: &Key, value: &[u8]) -> Result<()> {
+        self.0.write_bytes(&shadow_key(key), value)
+    }
+
+    fn delete(&mut self, key: &Key) -> Result<()> {
+        self.0.write_bytes(&shadow_key(key), Vec::<u8>::new())
+    }
+}
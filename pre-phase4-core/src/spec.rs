@@ -0,0 +1,248 @@
+//! The declarative specification of the migration carried in the data of the
+//! proposal, so that a single audited WASM can be reused by proposals
+//! resetting different tokens, over different epochs, or on a network with a
+//! different native token. Proposals without data, or leaving a setting unset,
+//! fall back to the values compiled into the WASM.
+
+use std::collections::BTreeSet;
+
+use masp::{MaspEpoch, Precision};
+use namada_tx_prelude::*;
+use serde::{Deserialize, Serialize};
+use token::Denomination;
+
+use crate::{EpochWindow, RewardScale};
+
+/// The version of the specification format understood by this WASM. It is
+/// encoded first, so that a specification of another version is rejected with
+/// a descriptive error before the rest of it is decoded.
+pub const SPEC_VERSION: u8 = 6;
+
+/// A specification of the migration. Proposal authors write it as JSON, and it
+/// is Borsh encoded in the proposal data.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub struct UpgradeSpec {
+    /// The version of the specification format
+    pub version: u8,
+    /// The non-native tokens to reset
+    pub tokens: Option<Vec<TokenSpec>>,
+    /// The tokens to leave out of the reset, for instance to carve a token out
+    /// of the table compiled into the WASM
+    pub exclude: Option<Vec<Address>>,
+    /// The MASP epoch in which the migration will be applied
+    pub target_epoch: Option<MaspEpoch>,
+    /// The window of MASP epochs whose conversions are reset
+    pub reset_epochs: Option<EpochWindow>,
+    /// The address of the native token, which must be the native token of the
+    /// chain executing the migration
+    pub native_token: Option<Address>,
+    /// How the reward precisions of the tokens are updated
+    pub precision_mode: Option<PrecisionMode>,
+    /// Whether the reward precisions are written to the live keys or
+    /// scheduled for the target epoch
+    pub application_mode: Option<ApplicationMode>,
+}
+
+/// A token reset, rescaled, or pruned by the migration
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub struct TokenSpec {
+    /// The address of the token, unless it is given by its symbol
+    pub address: Option<Address>,
+    /// The symbol of the token in the token registry, resolved through the
+    /// MASP token map at execution time so that a token whose IBC address
+    /// changed with a channel migration is still found
+    pub symbol: Option<String>,
+    /// The denomination of the token, or `None` to take the one stored on
+    /// chain
+    pub denomination: Option<Denomination>,
+    /// The new reward precision of the token, or the precision its terminal
+    /// conversions are computed with when it is pruned
+    pub precision: Precision,
+    /// What the migration does to the token, or `None` to reset it
+    pub operation: Option<TokenOperation>,
+    /// The window of MASP epochs whose conversions are reset, for a token
+    /// onboarded after the first epochs of the reset
+    pub epochs: Option<EpochWindow>,
+}
+
+/// What the migration does to a token
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub enum TokenOperation {
+    /// Erase the rewards distributed so far and schedule the new precision
+    Reset,
+    /// Multiply the rewards of the conversions already scheduled for the
+    /// target epoch by the given factor rather than erasing them. Fails if the
+    /// token has no conversions scheduled.
+    Rescale(RewardScale),
+    /// Remove the token from the shielded pool: convert its notes to the
+    /// target epoch one last time and zero its rewards
+    Prune,
+}
+
+/// How the migration updates the reward precisions of the tokens
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub enum PrecisionMode {
+    /// Schedule the precisions of the specification
+    Reset,
+    /// Keep the current reward precision of every token and only reset its
+    /// conversions
+    Keep,
+}
+
+/// When the migration lets the updated reward precisions take effect
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+#[borsh(crate = "namada_tx_prelude::borsh")]
+pub enum ApplicationMode {
+    /// Write the precisions under the scheduled keys of the target epoch, for
+    /// the protocol to apply along with the conversion updates
+    Scheduled,
+    /// Write the precisions to the live keys, so that they already take
+    /// effect in the conversions the protocol computes before the target epoch
+    Immediate,
+}
+
+impl UpgradeSpec {
+    /// Decode the specification from the data of the given transaction, if it
+    /// carries any, and validate it. Fails if the data is not a valid
+    /// specification, so that a malformed proposal is rejected rather than
+    /// applied with the values compiled into the WASM.
+    pub fn from_tx_data(tx_data: &BatchedTx) -> Result<Option<Self>> {
+        let Some(data) = tx_data.tx.data(&tx_data.cmt) else {
+            return Ok(None);
+        };
+        if data.is_empty() {
+            return Ok(None);
+        }
+        // Check the version before decoding a layout it may not have
+        check_version(data[0])?;
+        let spec = Self::try_from_slice(&data).map_err(|err| {
+            Error::new_alloc(format!("invalid upgrade spec v{SPEC_VERSION}: {err}"))
+        })?;
+        spec.validate()?;
+        Ok(Some(spec))
+    }
+
+    /// Check that the specification has the supported version, lists every
+    /// token at most once, by either its address or its symbol, with a
+    /// positive precision, a well-formed scale, and an ordered window of epochs
+    /// if it is reset, and excludes no listed token
+    pub fn validate(&self) -> TxResult {
+        check_version(self.version)?;
+        let mut addresses = BTreeSet::new();
+        let mut symbols = BTreeSet::new();
+        for token in self.tokens.iter().flatten() {
+            let listed_once = match (&token.address, &token.symbol) {
+                (Some(address), None) => addresses.insert(address),
+                (None, Some(symbol)) => symbols.insert(symbol),
+                _ => {
+                    return Err(Error::new_const(
+                        "every token must be given by either its address or its symbol",
+                    ))
+                }
+            };
+            let name = token.name();
+            if !listed_once {
+                return Err(Error::new_alloc(format!(
+                    "token {name} is listed more than once"
+                )));
+            }
+            if token.precision == 0 {
+                return Err(Error::new_alloc(format!(
+                    "the precision of token {name} is zero"
+                )));
+            }
+            if let Some(TokenOperation::Rescale((_, 0))) = token.operation {
+                return Err(Error::new_alloc(format!(
+                    "the scale of token {name} has a zero denominator"
+                )));
+            }
+            if let Some((from_epoch, to_epoch)) = token.epochs {
+                if !matches!(token.operation, None | Some(TokenOperation::Reset)) {
+                    return Err(Error::new_alloc(format!(
+                        "token {name} has a window of epochs but is not reset"
+                    )));
+                }
+                if from_epoch > to_epoch {
+                    return Err(Error::new_alloc(format!(
+                        "the window of epochs of token {name} must be ordered"
+                    )));
+                }
+            }
+        }
+        let mut excluded = BTreeSet::new();
+        for token_address in self.exclude.iter().flatten() {
+            if !excluded.insert(token_address) {
+                return Err(Error::new_alloc(format!(
+                    "token {token_address} is excluded more than once"
+                )));
+            }
+            if addresses.contains(token_address) {
+                return Err(Error::new_alloc(format!(
+                    "token {token_address} is both listed and excluded"
+                )));
+            }
+        }
+        if let Some((window_start, window_end)) = self.reset_epochs {
+            if window_start > window_end {
+                return Err(Error::new_const("the reset epoch window must be ordered"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TokenSpec {
+    /// Get the address or the symbol by which the token is given, for error
+    /// messages
+    pub fn name(&self) -> String {
+        match (&self.address, &self.symbol) {
+            (Some(address), _) => address.to_string(),
+            (None, Some(symbol)) => symbol.clone(),
+            (None, None) => "<unnamed>".to_owned(),
+        }
+    }
+}
+
+/// Check that a specification of the given version can be executed by this
+/// WASM, with an error telling which side is outdated otherwise
+fn check_version(version: u8) -> TxResult {
+    if version < SPEC_VERSION {
+        return Err(Error::new_alloc(format!(
+            "upgrade spec v{SPEC_VERSION} required, got v{version}: encode the spec in the \
+             format of this WASM"
+        )));
+    }
+    if version > SPEC_VERSION {
+        return Err(Error::new_alloc(format!(
+            "upgrade spec v{version} is newer than the v{SPEC_VERSION} supported by this WASM: \
+             submit the WASM of the release that introduced v{version}"
+        )));
+    }
+    Ok(())
+}
+
+impl Default for UpgradeSpec {
+    /// A specification of the current version leaving every setting unset
+    fn default() -> Self {
+        Self {
+            version: SPEC_VERSION,
+            tokens: None,
+            exclude: None,
+            target_epoch: None,
+            reset_epochs: None,
+            native_token: None,
+            precision_mode: None,
+            application_mode: None,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Plan a reset targeting tens of thousands of MASP epochs and check that the
+//! number of conversions, the peak memory, and the runtime of the planner stay
+//! within the bounds below, so that the approach keeps working as the chain
+//! ages. Run with `cargo test --release -p pre-phase4-core --test soak -- --ignored`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use namada_tx_prelude::masp::MaspEpoch;
+use namada_tx_prelude::token::{Denomination, MaspDigitPos};
+use namada_tx_prelude::*;
+// Link the native host functions imported by the transaction
+use namada_tests as _;
+
+/// The MASP epoch targeted by the soak test
+const TARGET_EPOCH: u64 = 20_000;
+/// The maximum time the plan may take in a release build
+const MAX_RUNTIME: Duration = Duration::from_secs(300);
+/// The maximum number of bytes that may be allocated at once while planning
+const MAX_PEAK_MEMORY: usize = 1 << 30;
+
+/// An allocator tracking the number of bytes allocated through it, and the
+/// highest number of bytes allocated at once
+struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+fn masp_epoch(epoch: u64) -> MaspEpoch {
+    MaspEpoch::try_from_epoch(Epoch(epoch), 1).unwrap()
+}
+
+#[test]
+#[ignore = "plans conversions for tens of thousands of epochs"]
+fn test_plan_over_large_target_epoch() {
+    let tokens = [(
+        ibc::ibc_token("transfer/channel-1/uosmo").clone(),
+        Denomination(0u8),
+        100_000_000u128,
+    )];
+    let native = (
+        Address::decode(test_utils::NATIVE_TOKEN).unwrap(),
+        Denomination(6u8),
+        1_000_000_000u128,
+    );
+    let target_masp_epoch = masp_epoch(TARGET_EPOCH);
+    let reset_epochs = (MaspEpoch::zero(), masp_epoch(TARGET_EPOCH - 1));
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let start = Instant::now();
+    let matrix =
+        pre_phase4_core::conversion_matrix(&tokens, &native, &target_masp_epoch, &reset_epochs).unwrap();
+    let runtime = start.elapsed();
+    let peak_memory = PEAK.load(Ordering::Relaxed) - baseline;
+
+    // A conversion per token, reset epoch, and digit, along with those of
+    // the target epoch asset types if enabled
+    let tokens = tokens.len() + 1;
+    let digits = MaspDigitPos::iter().count();
+    let epochs = usize::try_from(TARGET_EPOCH).unwrap();
+    assert!(
+        (tokens * epochs * digits..=tokens * (epochs + 1) * digits).contains(&matrix.len()),
+        "planned {} conversions",
+        matrix.len()
+    );
+    assert!(
+        runtime <= MAX_RUNTIME,
+        "planning {} conversions took {runtime:?}",
+        matrix.len()
+    );
+    assert!(
+        peak_memory <= MAX_PEAK_MEMORY,
+        "planning {} conversions allocated up to {peak_memory} bytes",
+        matrix.len()
+    );
+}
@@ -0,0 +1,147 @@
+//! Checks of the governance proposal JSON files committed under
+//! `builder/proposals`, which record the proposals built from the parameter
+//! files under `builder/parameters` for submission.
+//!
+//! The committed files leave out the WASM code, which `build_proposal.py`
+//! embeds as the `data` field from the `wasm_path` of the parameter file. The
+//! tests check that the files deserialize with the governance types of the
+//! node, that they agree with their parameter files, and that the WASM paths
+//! name artifacts that the workspace actually builds.
+
+use std::path::{Path, PathBuf};
+
+/// The proposals whose JSON files are committed
+pub const PROPOSALS: [&str; 4] = ["phase2", "phase3", "phase4", "pre-phase4"];
+
+/// Get the root of the repository
+pub fn repository() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Read the committed JSON file of the given proposal
+pub fn read_proposal(name: &str) -> serde_json::Value {
+    read_json(&repository().join(format!("builder/proposals/{name}.json")))
+}
+
+/// Read the parameter file from which the given proposal is built
+pub fn read_parameters(name: &str) -> serde_json::Value {
+    read_json(&repository().join(format!("builder/parameters/{name}.json")))
+}
+
+fn read_json(path: &Path) -> serde_json::Value {
+    let json = std::fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("unable to read {}: {error}", path.display()));
+    serde_json::from_str(&json)
+        .unwrap_or_else(|error| panic!("{} is not valid JSON: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use namada_governance::cli::onchain::DefaultProposal;
+
+    use super::*;
+
+    // The maximum size in bytes of the code of a governance proposal
+    const MAX_PROPOSAL_CODE_SIZE: usize = 300_000;
+
+    /// Get the names of the WASM files built by the workspace, i.e. the
+    /// library names of the members building a cdylib
+    fn built_artifacts() -> BTreeSet<String> {
+        let read_toml = |path: PathBuf| -> toml::Table {
+            std::fs::read_to_string(path).unwrap().parse().unwrap()
+        };
+        let workspace = read_toml(repository().join("Cargo.toml"));
+        let mut artifacts = BTreeSet::new();
+        for member in workspace["workspace"]["members"].as_array().unwrap() {
+            let manifest = read_toml(
+                repository()
+                    .join(member.as_str().unwrap())
+                    .join("Cargo.toml"),
+            );
+            let builds_cdylib = manifest
+                .get("lib")
+                .and_then(|lib| lib.get("crate-type"))
+                .and_then(|crate_types| crate_types.as_array())
+                .is_some_and(|crate_types| {
+                    crate_types.iter().any(|ty| ty.as_str() == Some("cdylib"))
+                });
+            if builds_cdylib {
+                let name = manifest["package"]["name"].as_str().unwrap();
+                artifacts.insert(format!("artifacts/{}.wasm", name.replace('-', "_")));
+            }
+        }
+        artifacts
+    }
+
+    #[test]
+    fn test_proposals_deserialize() {
+        for name in PROPOSALS {
+            let proposal: DefaultProposal = serde_json::from_value(read_proposal(name))
+                .unwrap_or_else(|error| panic!("{name} does not deserialize: {error}"));
+            assert!(proposal.data.is_none(), "{name} embeds its WASM code");
+
+            // The proposal is built from its parameter file
+            let parameters = read_parameters(name);
+            let proposal = proposal.proposal;
+            assert_eq!(proposal.author.to_string(), parameters["author"]);
+            for (epoch, field) in [
+                (proposal.voting_start_epoch, "voting_start_epoch"),
+                (proposal.voting_end_epoch, "voting_end_epoch"),
+                (proposal.activation_epoch, "activation_epoch"),
+            ] {
+                assert_eq!(
+                    Some(epoch.0),
+                    parameters[field].as_u64(),
+                    "{name} has a different {field} than its parameters"
+                );
+            }
+            for field in ["title", "authors", "abstract", "motivation", "details"] {
+                assert_eq!(
+                    proposal.content.get(field).map(String::as_str),
+                    parameters[field].as_str(),
+                    "{name} has a different {field} than its parameters"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_wasm_paths_reference_built_artifacts() {
+        let artifacts = built_artifacts();
+        for name in PROPOSALS {
+            let wasm_path = read_parameters(name)["wasm_path"]
+                .as_str()
+                .unwrap()
+                .to_owned();
+            assert!(
+                artifacts.contains(&wasm_path),
+                "{name} is built from {wasm_path}, which no member of the workspace builds"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "requires the artifacts of `earthly +build`"]
+    fn test_proposals_round_trip_with_built_artifacts() {
+        for name in PROPOSALS {
+            let wasm_path = read_parameters(name)["wasm_path"]
+                .as_str()
+                .unwrap()
+                .to_owned();
+            let wasm = std::fs::read(repository().join(&wasm_path)).unwrap();
+            assert!(
+                wasm.len() <= MAX_PROPOSAL_CODE_SIZE,
+                "{wasm_path} exceeds the maximum proposal code size"
+            );
+
+            // Embed the code the way the builder does for the submission
+            let mut proposal = read_proposal(name);
+            proposal["data"] = serde_json::json!(wasm);
+            let json = serde_json::to_string(&proposal).unwrap();
+            let proposal: DefaultProposal = serde_json::from_str(&json).unwrap();
+            assert_eq!(proposal.data, Some(wasm));
+        }
+    }
+}
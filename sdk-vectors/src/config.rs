@@ -0,0 +1,8 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    /// Where to write the vectors
+    #[clap(long, env, default_value = "pre-phase4-core/vectors/sdk.json")]
+    pub output: PathBuf,
+}
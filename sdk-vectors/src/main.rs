@@ -0,0 +1,82 @@
+//! Export the asset types and the encoded reset conversions of the tokens of
+//! the pre-phase4 table, as computed by the Namada SDK at the pinned version.
+//! The conformance tests of pre-phase4 check its own encodings against these
+//! vectors, so that a prelude upgrade that changes an encoding fails loudly
+//! instead of producing conversions for asset types that no note holds.
+
+pub mod config;
+
+use clap::Parser;
+use config::AppConfig;
+use namada_core::address::Address;
+use namada_core::chain::Epoch;
+use namada_core::masp::{encode_asset_type, MaspEpoch};
+use namada_core::masp_primitives::convert::AllowedConversion;
+use namada_core::masp_primitives::transaction::components::I128Sum;
+use namada_core::token::{Denomination, MaspDigitPos};
+use serde_json::json;
+// Link the native host functions imported by the transaction
+use test_utils as _;
+
+/// The epochs whose asset types are exported
+const EPOCHS: [u64; 3] = [0, 1, 210];
+/// The target epoch of the exported conversions
+const TARGET_EPOCH: u64 = 211;
+
+fn masp_epoch(epoch: u64) -> MaspEpoch {
+    MaspEpoch::try_from_epoch(Epoch(epoch), 1).unwrap()
+}
+
+fn main() {
+    let config = AppConfig::parse();
+
+    let mut asset_types = Vec::new();
+    let mut conversions = Vec::new();
+    for (token_address, denomination, precision) in &pre_phase4_core::TOKENS {
+        // The SDK may depend on a different build of the address types
+        let token = token_address.to_address().to_string();
+        let sdk_token = Address::decode(&token).unwrap();
+        let denomination = Denomination(denomination.0);
+        let precision = i128::try_from(*precision).unwrap();
+        for digit in MaspDigitPos::iter() {
+            let asset_type = |epoch| {
+                encode_asset_type(sdk_token.clone(), denomination, digit, Some(epoch)).unwrap()
+            };
+            for epoch in EPOCHS {
+                asset_types.push(json!({
+                    "token": token,
+                    "denomination": denomination.0,
+                    "digit": digit as u8,
+                    "epoch": epoch,
+                    "asset_type": asset_type(masp_epoch(epoch)).to_string(),
+                }));
+                // -PRECISION TOK[ep, digit] + PRECISION TOK[target_ep, digit]
+                let conversion: AllowedConversion =
+                    (-I128Sum::from_pair(asset_type(masp_epoch(epoch)), precision)
+                        + I128Sum::from_pair(asset_type(masp_epoch(TARGET_EPOCH)), precision))
+                    .into();
+                conversions.push(json!({
+                    "token": token,
+                    "denomination": denomination.0,
+                    "precision": precision.to_string(),
+                    "digit": digit as u8,
+                    "epoch": epoch,
+                    "target_epoch": TARGET_EPOCH,
+                    "conversion": hex::encode(borsh::to_vec(&conversion).unwrap()),
+                }));
+            }
+        }
+    }
+
+    let vectors = json!({
+        "sdk_version": "libs-v0.149.1",
+        "asset_types": asset_types,
+        "conversions": conversions,
+    });
+    std::fs::write(
+        &config.output,
+        serde_json::to_string_pretty(&vectors).unwrap(),
+    )
+    .unwrap();
+    println!("Wrote the vectors to {}", config.output.display());
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    /// The exported chain state, with a line per key holding the key and the
+    /// hex encoding of its value
+    #[clap(long, env)]
+    pub state: PathBuf,
+
+    /// The address of the native token of the chain
+    #[clap(long, env, default_value = test_utils::NATIVE_TOKEN)]
+    pub native_token: String,
+
+    /// The epoch of the block in which the proposal is executed
+    #[clap(long, env)]
+    pub epoch: Option<u64>,
+
+    /// The upgrade spec carried in the data of the proposal, as JSON
+    #[clap(long, env)]
+    pub spec: Option<PathBuf>,
+
+    /// Where to write the Borsh encoding of the upgrade spec, to be attached
+    /// as the data of the proposal
+    #[clap(long, env, requires = "spec")]
+    pub encode_spec: Option<PathBuf>,
+
+    /// Print a report of the planned writes per token in the given format
+    /// instead of the full storage diff
+    #[clap(long, env, value_enum)]
+    pub report: Option<ReportFormat>,
+
+    /// Where to write the log of every write of the migration, with a line
+    /// per write holding the key and the hash of the value
+    #[clap(long, env)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Where to write the values that the migration overwrites or deletes, as
+    /// a JSON object mapping every changed key to the hex encoding of its
+    /// value before the migration, or `null` for the keys it creates, so that
+    /// a rollback proposal can write them back
+    #[clap(long, env)]
+    pub undo_log: Option<PathBuf>,
+
+    /// Where to export the resulting chain state
+    #[clap(long, env)]
+    pub dump_to: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
@@ -0,0 +1,78 @@
+//! Execute the pre-phase4 migration against an exported chain state and
+//! print the resulting storage diff, so that operators can rehearse the exact
+//! effect of the proposal before voting. Given the upgrade spec of the
+//! proposal as JSON, it also writes the Borsh encoding to attach as the data
+//! of the proposal.
+
+pub mod config;
+pub mod report;
+
+use clap::Parser;
+use config::{AppConfig, ReportFormat};
+use namada_tx_prelude::*;
+use pre_phase4_core::audit::{self, AuditLog};
+use pre_phase4_core::UpgradeSpec;
+use test_utils::{read_state_export, write_state_export, MockCtx};
+
+fn main() {
+    let config = AppConfig::parse();
+
+    let initial = read_state_export(&config.state).unwrap();
+    let native_token = Address::decode(&config.native_token).unwrap();
+    let mut ctx = MockCtx::new(native_token, initial.clone());
+    if let Some(epoch) = config.epoch {
+        ctx.block_epoch = Epoch(epoch);
+    }
+
+    let spec: Option<UpgradeSpec> = config.spec.as_ref().map(|path| {
+        let spec = std::fs::read_to_string(path).unwrap();
+        serde_json::from_str(&spec).unwrap()
+    });
+    if let (Some(spec), Some(path)) = (&spec, &config.encode_spec) {
+        spec.validate().unwrap();
+        std::fs::write(path, borsh::to_vec(spec).unwrap()).unwrap();
+    }
+
+    let mut log = AuditLog::new(&mut ctx);
+    let summaries = pre_phase4_core::apply_spec(&mut log, spec.as_ref()).unwrap();
+    if let Some(path) = &config.audit_log {
+        std::fs::write(path, audit::render(log.entries())).unwrap();
+    }
+
+    if let Some(path) = &config.undo_log {
+        std::fs::write(path, report::undo_log(&initial, &ctx.diff())).unwrap();
+    }
+
+    if let Some(format) = config.report {
+        let report = match format {
+            ReportFormat::Markdown => report::markdown(&summaries),
+            ReportFormat::Json => report::json(&summaries),
+        };
+        println!("{report}");
+        return;
+    }
+
+    for (key, value) in ctx.diff() {
+        let before = initial
+            .get(&key)
+            .map(hex::encode)
+            .unwrap_or_else(|| "(none)".to_owned());
+        let after = value
+            .map(hex::encode)
+            .unwrap_or_else(|| "(deleted)".to_owned());
+        println!("{key}\n  before: {before}\n  after:  {after}");
+    }
+    for summary in &summaries {
+        println!(
+            "Token {}: {} epochs, {} conversions, digest {}",
+            summary.token,
+            summary.epochs,
+            summary.conversions.len(),
+            summary.digest(),
+        );
+    }
+
+    if let Some(path) = config.dump_to {
+        write_state_export(ctx.storage(), path).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+//! Reports of the planned writes of the migration, in Markdown for the
+//! governance forum post or in JSON for further processing.
+
+use std::collections::BTreeMap;
+
+use namada_tx_prelude::storage::Key;
+use pre_phase4_core::ResetSummary;
+use serde::Serialize;
+
+/// The planned writes concerning a single token
+#[derive(Serialize)]
+pub struct TokenReport {
+    pub token: String,
+    pub precision: Option<u128>,
+    pub first_epoch: Option<String>,
+    pub last_epoch: Option<String>,
+    pub epochs: u64,
+    pub keys: usize,
+    pub digest: String,
+}
+
+impl From<&ResetSummary> for TokenReport {
+    fn from(summary: &ResetSummary) -> Self {
+        Self {
+            token: summary.token.to_string(),
+            precision: summary.precision,
+            first_epoch: summary.epoch_range.map(|(first, _)| first.to_string()),
+            last_epoch: summary.epoch_range.map(|(_, last)| last.to_string()),
+            epochs: summary.epochs,
+            keys: summary.keys.len(),
+            digest: summary.digest().to_string(),
+        }
+    }
+}
+
+/// Render the planned writes as a Markdown table with a row per token
+pub fn markdown(summaries: &[ResetSummary]) -> String {
+    let mut report = String::from(
+        "| Token | Precision | Epochs | Keys | Digest |\n\
+         | ----- | --------- | ------ | ---- | ------ |\n",
+    );
+    for token in summaries.iter().map(TokenReport::from) {
+        let precision = token
+            .precision
+            .map(|precision| precision.to_string())
+            .unwrap_or_else(|| "unchanged".to_owned());
+        let epochs = match (&token.first_epoch, &token.last_epoch) {
+            (Some(first), Some(last)) => format!("{first} to {last} ({})", token.epochs),
+            _ => "none".to_owned(),
+        };
+        report.push_str(&format!(
+            "| `{}` | {precision} | {epochs} | {} | `{}` |\n",
+            token.token, token.keys, token.digest,
+        ));
+    }
+    report
+}
+
+/// Render the planned writes as a JSON array with an object per token
+pub fn json(summaries: &[ResetSummary]) -> String {
+    let tokens: Vec<_> = summaries.iter().map(TokenReport::from).collect();
+    serde_json::to_string_pretty(&tokens).expect("unable to render report")
+}
+
+/// Render the values that the migration overwrites or deletes as a JSON object
+/// mapping every changed key to the hex encoding of its value in the initial
+/// state, or `null` for the keys the migration creates
+pub fn undo_log(initial: &BTreeMap<Key, Vec<u8>>, diff: &BTreeMap<Key, Option<Vec<u8>>>) -> String {
+    let entries: BTreeMap<_, _> = diff
+        .keys()
+        .map(|key| (key.to_string(), initial.get(key).map(hex::encode)))
+        .collect();
+    serde_json::to_string_pretty(&entries).expect("unable to render undo log")
+}
@@ -0,0 +1,115 @@
+//! A generic storage layered over another one, so that an adapter only
+//! implements the accesses it intercepts.
+
+use std::ops::{Deref, DerefMut};
+
+use namada_tx_prelude::chain::{BlockHeader, BlockHeight, ChainId, Epochs};
+use namada_tx_prelude::storage::{Key, TxIndex};
+use namada_tx_prelude::*;
+
+/// The accesses that a storage adapter intercepts on their way to the storage
+/// it is layered over
+pub trait Adapter {
+    /// The storage that the adapter is layered over
+    type Inner: StorageRead;
+
+    /// Get the storage that the adapter is layered over
+    fn inner(&self) -> &Self::Inner;
+
+    /// Read the value of the given key, from the inner storage unless the
+    /// adapter overrides it
+    fn read_bytes(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        self.inner().read_bytes(key)
+    }
+
+    /// Write the given value under the given key
+    fn write_bytes(&mut self, key: &Key, value: &[u8]) -> Result<()>;
+
+    /// Delete the given key
+    fn delete(&mut self, key: &Key) -> Result<()>;
+}
+
+/// A storage going through the given adapter for its reads and writes, and
+/// forwarding the prefix iteration and the chain and block metadata to the
+/// inner storage of the adapter. Prefix iteration thus only sees the keys of
+/// the inner storage, as the migrations never iterate over the keys they
+/// write.
+pub struct Adapted<A>(pub A);
+
+impl<A> Deref for Adapted<A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.0
+    }
+}
+
+impl<A> DerefMut for Adapted<A> {
+    fn deref_mut(&mut self) -> &mut A {
+        &mut self.0
+    }
+}
+
+impl<A: Adapter> StorageRead for Adapted<A> {
+    type PrefixIter<'iter>
+        = <A::Inner as StorageRead>::PrefixIter<'iter>
+    where
+        Self: 'iter;
+
+    fn read_bytes(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        self.0.read_bytes(key)
+    }
+
+    fn has_key(&self, key: &Key) -> Result<bool> {
+        Ok(self.0.read_bytes(key)?.is_some())
+    }
+
+    fn iter_prefix<'iter>(&'iter self, prefix: &Key) -> Result<Self::PrefixIter<'iter>> {
+        self.0.inner().iter_prefix(prefix)
+    }
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>> {
+        self.0.inner().iter_next(iter)
+    }
+
+    fn get_chain_id(&self) -> Result<ChainId> {
+        self.0.inner().get_chain_id()
+    }
+
+    fn get_block_height(&self) -> Result<BlockHeight> {
+        self.0.inner().get_block_height()
+    }
+
+    fn get_block_header(&self, height: BlockHeight) -> Result<Option<BlockHeader>> {
+        self.0.inner().get_block_header(height)
+    }
+
+    fn get_block_epoch(&self) -> Result<Epoch> {
+        self.0.inner().get_block_epoch()
+    }
+
+    fn get_pred_epochs(&self) -> Result<Epochs> {
+        self.0.inner().get_pred_epochs()
+    }
+
+    fn get_tx_index(&self) -> Result<TxIndex> {
+        self.0.inner().get_tx_index()
+    }
+
+    fn get_native_token(&self) -> Result<Address> {
+        self.0.inner().get_native_token()
+    }
+}
+
+impl<A: Adapter> StorageWrite for Adapted<A> {
+    fn write_bytes(&mut self, key: &Key, val: impl AsRef<[u8]>) -> Result<()> {
+        self.0.write_bytes(key, val.as_ref())
+    }
+
+    fn delete(&mut self, key: &Key) -> Result<()> {
+        self.0.delete(key)
+    }
+}
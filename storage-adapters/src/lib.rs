@@ -0,0 +1,233 @@
+//! In-memory storage and storage adapters shared by the governance
+//! transactions and their tooling. [`MockCtx`] implements [`StorageRead`] and
+//! [`StorageWrite`] over a [`BTreeMap`], so the logic of a transaction written
+//! against these traits can be executed without a node. [`Adapted`] layers an
+//! [`Adapter`] over another storage, forwarding everything the adapter does
+//! not intercept.
+
+mod adapter;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use namada_tx_prelude::chain::{BlockHeader, BlockHeight, ChainId, Epoch, Epochs};
+use namada_tx_prelude::storage::{Key, TxIndex};
+use namada_tx_prelude::*;
+
+pub use adapter::{Adapted, Adapter};
+
+/// The address of the native token on mainnet
+pub const NATIVE_TOKEN: &str = "tnam1q9gr66cvu4hrzm0sd5kmlnjje82gs3xlfg3v6nu7";
+
+/// An access to the storage made through a [`MockCtx`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageAccess {
+    /// A read of the given key, which holds a value of the given length
+    Read { key: Key, len: usize },
+    /// A write of a value of the given length under the given key
+    Write { key: Key, len: usize },
+    /// A deletion of the given key
+    Delete { key: Key },
+}
+
+/// An in-memory storage context
+#[derive(Debug, Clone)]
+pub struct MockCtx {
+    /// The storage before the transaction
+    initial: BTreeMap<Key, Vec<u8>>,
+    /// The storage as modified by the transaction
+    storage: BTreeMap<Key, Vec<u8>>,
+    /// The ID of the chain
+    pub chain_id: ChainId,
+    /// The height of the current block
+    pub block_height: BlockHeight,
+    /// The epoch of the current block
+    pub block_epoch: Epoch,
+    /// The first block heights of every epoch
+    pub pred_epochs: Epochs,
+    /// The index of the transaction in the current block
+    pub tx_index: TxIndex,
+    /// The address of the native token
+    pub native_token: Address,
+    /// Every access made to the storage, in order
+    accesses: RefCell<Vec<StorageAccess>>,
+}
+
+impl Default for MockCtx {
+    /// Create a context over an empty storage with the mainnet native token
+    fn default() -> Self {
+        let native_token = Address::decode(NATIVE_TOKEN).expect("invalid native token address");
+        Self::new(native_token, BTreeMap::new())
+    }
+}
+
+impl MockCtx {
+    /// Create a context over the given initial storage
+    pub fn new(native_token: Address, initial: BTreeMap<Key, Vec<u8>>) -> Self {
+        Self {
+            storage: initial.clone(),
+            initial,
+            chain_id: ChainId::default(),
+            block_height: BlockHeight::default(),
+            block_epoch: Epoch::default(),
+            pred_epochs: Epochs::default(),
+            tx_index: TxIndex::default(),
+            native_token,
+            accesses: RefCell::default(),
+        }
+    }
+
+    /// Get every access made to the storage so far, in order
+    pub fn accesses(&self) -> Vec<StorageAccess> {
+        self.accesses.borrow().clone()
+    }
+
+    /// Write the given value into the initial storage, as if it was stored
+    /// before the transaction
+    pub fn init<T: BorshSerialize>(&mut self, key: &Key, value: T) {
+        let value = borsh::to_vec(&value).expect("unable to encode value");
+        self.initial.insert(key.clone(), value.clone());
+        self.storage.insert(key.clone(), value);
+    }
+
+    /// Make the storage as modified so far the initial storage, e.g. after
+    /// setting up the state with helpers that write through the context, and
+    /// forget the accesses made so far
+    pub fn commit(&mut self) {
+        self.initial = self.storage.clone();
+        self.accesses.get_mut().clear();
+    }
+
+    /// Get the storage as modified by the transaction
+    pub fn storage(&self) -> &BTreeMap<Key, Vec<u8>> {
+        &self.storage
+    }
+
+    /// Get every key changed by the transaction along with its new value, or
+    /// `None` if it was deleted. Keys written with their initial value are
+    /// left out.
+    pub fn diff(&self) -> BTreeMap<Key, Option<Vec<u8>>> {
+        let mut diff = BTreeMap::new();
+        for (key, value) in &self.storage {
+            if self.initial.get(key) != Some(value) {
+                diff.insert(key.clone(), Some(value.clone()));
+            }
+        }
+        for key in self.initial.keys() {
+            if !self.storage.contains_key(key) {
+                diff.insert(key.clone(), None);
+            }
+        }
+        diff
+    }
+}
+
+impl StorageRead for MockCtx {
+    type PrefixIter<'iter> = std::vec::IntoIter<(String, Vec<u8>)>;
+
+    fn read_bytes(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        let value = self.storage.get(key).cloned();
+        self.accesses.borrow_mut().push(StorageAccess::Read {
+            key: key.clone(),
+            len: value.as_ref().map_or(0, Vec::len),
+        });
+        Ok(value)
+    }
+
+    fn has_key(&self, key: &Key) -> Result<bool> {
+        Ok(self.storage.contains_key(key))
+    }
+
+    fn iter_prefix<'iter>(&'iter self, prefix: &Key) -> Result<Self::PrefixIter<'iter>> {
+        Ok(self
+            .storage
+            .iter()
+            .filter(|(key, _)| key.segments.starts_with(&prefix.segments))
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>> {
+        Ok(iter.next())
+    }
+
+    fn get_chain_id(&self) -> Result<ChainId> {
+        Ok(self.chain_id.clone())
+    }
+
+    fn get_block_height(&self) -> Result<BlockHeight> {
+        Ok(self.block_height)
+    }
+
+    fn get_block_header(&self, _height: BlockHeight) -> Result<Option<BlockHeader>> {
+        Ok(None)
+    }
+
+    fn get_block_epoch(&self) -> Result<Epoch> {
+        Ok(self.block_epoch)
+    }
+
+    fn get_pred_epochs(&self) -> Result<Epochs> {
+        Ok(self.pred_epochs.clone())
+    }
+
+    fn get_tx_index(&self) -> Result<TxIndex> {
+        Ok(self.tx_index)
+    }
+
+    fn get_native_token(&self) -> Result<Address> {
+        Ok(self.native_token.clone())
+    }
+}
+
+impl StorageWrite for MockCtx {
+    fn write_bytes(&mut self, key: &Key, val: impl AsRef<[u8]>) -> Result<()> {
+        self.accesses.borrow_mut().push(StorageAccess::Write {
+            key: key.clone(),
+            len: val.as_ref().len(),
+        });
+        self.storage.insert(key.clone(), val.as_ref().to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &Key) -> Result<()> {
+        self.accesses
+            .borrow_mut()
+            .push(StorageAccess::Delete { key: key.clone() });
+        self.storage.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_tracks_writes_and_deletes() {
+        let key = |name: &str| Key::parse(name).unwrap();
+        let mut ctx = MockCtx::default();
+        ctx.init(&key("unchanged"), 1u64);
+        ctx.init(&key("deleted"), 2u64);
+        ctx.init(&key("rewritten"), 3u64);
+
+        ctx.write(&key("unchanged"), 1u64).unwrap();
+        ctx.delete(&key("deleted")).unwrap();
+        ctx.write(&key("rewritten"), 4u64).unwrap();
+        ctx.write(&key("created"), 5u64).unwrap();
+
+        assert_eq!(ctx.read::<u64>(&key("rewritten")).unwrap(), Some(4));
+        assert_eq!(
+            ctx.diff(),
+            BTreeMap::from([
+                (key("created"), Some(borsh::to_vec(&5u64).unwrap())),
+                (key("deleted"), None),
+                (key("rewritten"), Some(borsh::to_vec(&4u64).unwrap())),
+            ])
+        );
+    }
+}
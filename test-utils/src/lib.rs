@@ -0,0 +1,99 @@
+//! Helpers for unit testing the governance transactions without a node. The
+//! in-memory [`MockCtx`] executes the logic of a transaction written against
+//! [`StorageRead`] and [`StorageWrite`] in `cargo test`, and the snapshot
+//! helpers assert its exact writes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use namada_tx_prelude::storage::Key;
+use namada_tx_prelude::*;
+// Link the native host functions imported by the transactions under test
+use namada_tests as _;
+pub use storage_adapters::{MockCtx, StorageAccess, NATIVE_TOKEN};
+
+/// Read a storage exported with a line per key, holding the key and the hex
+/// encoding of its value separated by a space
+pub fn read_state_export(path: impl AsRef<Path>) -> std::io::Result<BTreeMap<Key, Vec<u8>>> {
+    let invalid = |error: String| std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+    let mut storage = BTreeMap::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(' ')
+            .ok_or_else(|| invalid(format!("missing value in line {line}")))?;
+        let key = Key::parse(key).map_err(|error| invalid(error.to_string()))?;
+        let value = hex::decode(value.trim()).map_err(|error| invalid(error.to_string()))?;
+        storage.insert(key, value);
+    }
+    Ok(storage)
+}
+
+/// Render the given storage in the format read by [`read_state_export`]
+pub fn render_state_export(storage: &BTreeMap<Key, Vec<u8>>) -> String {
+    let mut export = String::new();
+    for (key, value) in storage {
+        writeln!(export, "{key} {}", hex::encode(value)).expect("unable to render export");
+    }
+    export
+}
+
+/// Export the given storage in the format read by [`read_state_export`]
+pub fn write_state_export(
+    storage: &BTreeMap<Key, Vec<u8>>,
+    path: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    std::fs::write(path, render_state_export(storage))
+}
+
+/// Render the given write set with a line per key, holding the key and either
+/// the hash of its new value or `deleted`
+pub fn render_snapshot(diff: &BTreeMap<Key, Option<Vec<u8>>>) -> String {
+    let mut snapshot = String::new();
+    for (key, value) in diff {
+        let value = match value {
+            Some(value) => hash::Hash::sha256(value).to_string(),
+            None => "deleted".to_owned(),
+        };
+        writeln!(snapshot, "{key} {value}").expect("unable to render snapshot");
+    }
+    snapshot
+}
+
+/// Assert that the given write set, e.g. the [`MockCtx::diff`] of a
+/// transaction, matches the snapshot committed at the given path, so that
+/// changes to the write set show up in review. The snapshot is written instead
+/// if the `UPDATE_SNAPSHOTS` environment variable is set, and a missing
+/// snapshot fails the assertion otherwise.
+pub fn assert_snapshot(diff: &BTreeMap<Key, Option<Vec<u8>>>, path: impl AsRef<Path>) {
+    assert_golden(&render_snapshot(diff), path)
+}
+
+/// Assert that the given rendered output matches the golden file committed at
+/// the given path, which is written the same way as by [`assert_snapshot`]
+pub fn assert_golden(snapshot: &str, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("unable to create snapshot directory");
+        }
+        std::fs::write(path, snapshot).expect("unable to write snapshot");
+        return;
+    }
+    assert!(
+        path.exists(),
+        "the snapshot {} is missing. Run with UPDATE_SNAPSHOTS=1 to write it and \
+         commit it.",
+        path.display(),
+    );
+    let expected = std::fs::read_to_string(path).expect("unable to read snapshot");
+    assert!(
+        snapshot == expected,
+        "the output does not match the snapshot {}. Rerun with UPDATE_SNAPSHOTS=1 to \
+         update it.\nExpected:\n{expected}\nActual:\n{snapshot}",
+        path.display(),
+    );
+}
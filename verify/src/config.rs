@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    #[clap(long, env)]
+    pub tendermint_url: String,
+
+    /// The chain state from before the proposal was executed, as exported by
+    /// the dry run
+    #[clap(long, env)]
+    pub state: PathBuf,
+
+    /// The epoch of the block in which the proposal was executed
+    #[clap(long, env)]
+    pub epoch: u64,
+
+    /// The height at which to query the chain state, the latest one if unset
+    #[clap(long, env)]
+    pub height: Option<u64>,
+}
@@ -0,0 +1,65 @@
+//! After the proposal has been executed on chain, query every key that the
+//! pre-phase4 migration should have written and check its value byte for
+//! byte against the expected one, computed by executing the migration over
+//! the chain state exported before the proposal was executed.
+
+pub mod config;
+
+use std::process;
+
+use clap::Parser;
+use config::AppConfig;
+use namada_sdk::rpc::{query_native_token, query_storage_value_bytes};
+use namada_tx_prelude::*;
+use tendermint_rpc::HttpClient;
+use test_utils::{read_state_export, MockCtx};
+
+#[tokio::main]
+async fn main() {
+    let config = AppConfig::parse();
+
+    let client = HttpClient::new(config.tendermint_url.as_str()).unwrap();
+    let native_token = query_native_token(&client).await.unwrap();
+    let native_token = Address::decode(native_token.encode()).unwrap();
+
+    // Compute the expected writes
+    let initial = read_state_export(&config.state).unwrap();
+    let mut ctx = MockCtx::new(native_token, initial);
+    ctx.block_epoch = Epoch(config.epoch);
+    pre_phase4_core::apply(&mut ctx).unwrap();
+
+    let height = config.height.map(namada_sdk::chain::BlockHeight);
+    let mut mismatches = 0;
+    let expected_writes = ctx.diff();
+    for (key, expected) in &expected_writes {
+        // The SDK may depend on a different build of the storage types
+        let sdk_key = namada_sdk::storage::Key::parse(key.to_string()).unwrap();
+        let (actual, _proof) = query_storage_value_bytes(&client, &sdk_key, height, false)
+            .await
+            .unwrap();
+        if &actual == expected {
+            println!("PASS {key}");
+        } else {
+            let render = |value: &Option<Vec<u8>>| {
+                value
+                    .as_ref()
+                    .map(hex::encode)
+                    .unwrap_or_else(|| "(none)".to_owned())
+            };
+            println!(
+                "FAIL {key}\n  expected: {}\n  actual:   {}",
+                render(expected),
+                render(&actual)
+            );
+            mismatches += 1;
+        }
+    }
+
+    println!(
+        "Checked {} keys, {mismatches} of which do not match",
+        expected_writes.len()
+    );
+    if mismatches > 0 {
+        process::exit(1)
+    }
+}
@@ -0,0 +1,83 @@
+//! Execute the optimized WASM of the pre-phase4 proposal in the same runtime
+//! as the node, against the host environment of the test suite. Compiling the
+//! native crate says nothing about the artifact that is actually submitted:
+//! a host function missing from the node, a floating point instruction
+//! introduced by the optimizer, or any other instruction the node rejects only
+//! surfaces once the validated WASM is instantiated and its entrypoint run.
+
+use std::collections::BTreeMap;
+
+use namada_tests::tx::TestTxEnv;
+use namada_tx_prelude::*;
+
+/// The path of the optimized pre-phase4 WASM built by `earthly +build`
+pub const ARTIFACT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32-unknown-unknown/release/pre_phase4.wasm"
+);
+
+/// Execute the given WASM as the code of a transaction in a fresh test
+/// environment on the chain it was built for and return the environment
+/// holding its writes
+pub fn execute(wasm: Vec<u8>) -> (TestTxEnv, Result<(), String>) {
+    run(TestTxEnv::default(), wasm)
+}
+
+/// Execute the given WASM like [`execute`], over a test environment holding
+/// the given storage, e.g. an exported chain state
+pub fn execute_over(
+    wasm: Vec<u8>,
+    storage: &BTreeMap<storage::Key, Vec<u8>>,
+) -> (TestTxEnv, Result<(), String>) {
+    let mut env = TestTxEnv::default();
+    for (key, value) in storage {
+        env.state
+            .write_bytes(key, value)
+            .expect("unable to write the initial storage");
+    }
+    env.commit_tx_and_block();
+    run(env, wasm)
+}
+
+/// Execute the given WASM as the code of a transaction in the given
+/// environment, on the chain it was built for
+fn run(mut env: TestTxEnv, wasm: Vec<u8>) -> (TestTxEnv, Result<(), String>) {
+    env.state.in_mem_mut().chain_id = chain::ChainId(pre_phase4_core::CHAIN_ID.to_owned());
+    let mut tx = Tx::from_type(transaction::TxType::Raw);
+    tx.add_code(wasm, None);
+    env.batched_tx = tx.batch_first_tx();
+    let result = env.execute_tx().map_err(|error| error.to_string());
+    (env, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masp::{MaspEpoch, Precision};
+    use token::storage_key::masp_scheduled_reward_precision_key;
+
+    #[test]
+    #[ignore = "requires the optimized artifacts of `earthly +build`"]
+    fn test_artifact_executes_in_node_runtime() {
+        let wasm_path = std::env::var("SMOKE_WASM").unwrap_or_else(|_| ARTIFACT.to_owned());
+        let wasm = std::fs::read(&wasm_path)
+            .unwrap_or_else(|error| panic!("unable to read {wasm_path}: {error}"));
+
+        let (env, result) = execute(wasm);
+        if let Err(error) = result {
+            panic!("{wasm_path} failed in the node runtime: {error}");
+        }
+
+        let target = MaspEpoch::try_from_epoch(Epoch(844), 4).unwrap();
+        for (token_address, _, precision) in &pre_phase4_core::TOKENS {
+            let scheduled_precision_key =
+                masp_scheduled_reward_precision_key(&target, &token_address.to_address());
+            assert_eq!(
+                env.state
+                    .read::<Precision>(&scheduled_precision_key)
+                    .unwrap(),
+                Some(*precision)
+            );
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct AppConfig {
+    #[clap(subcommand)]
+    pub task: Task,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Task {
+    /// Meter the gas of the optimized pre-phase4 WASM in the runtime of the
+    /// node and fail if it exceeds the gas budget of the governance execution
+    GasCheck {
+        /// The gas budget the transaction must fit into
+        #[clap(long, env = "GOVERNANCE_GAS_BUDGET", default_value_t = crate::MAX_BLOCK_GAS)]
+        budget: u64,
+    },
+
+    /// Execute the pre-phase4 transaction over an exported chain state and
+    /// print its storage diff
+    Simulate {
+        /// The exported chain state, as dumped by the dry run
+        #[clap(long, env)]
+        state: PathBuf,
+
+        /// The epoch of the block in which the proposal is executed
+        #[clap(long, env)]
+        epoch: Option<u64>,
+    },
+
+    /// Execute the pre-phase4 transaction over an exported chain state and
+    /// print a report of its planned writes per token
+    Report {
+        /// The exported chain state, as dumped by the dry run
+        #[clap(long, env)]
+        state: PathBuf,
+
+        /// The format of the report
+        #[clap(long, env, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+
+    /// Create a new phase crate, with its token table, manifest, and tests,
+    /// from a phase file, and add it to the workspace
+    NewPhase {
+        /// The phase file, with the name and description of the crate and the
+        /// symbols of its tokens in the token registry
+        #[clap(long)]
+        config: PathBuf,
+    },
+
+    /// Check the chain state after the proposal was executed against the
+    /// writes expected over the state exported before it
+    Verify {
+        #[clap(long, env)]
+        tendermint_url: String,
+
+        /// The exported chain state from before the proposal was executed
+        #[clap(long, env)]
+        state: PathBuf,
+
+        /// The epoch of the block in which the proposal was executed
+        #[clap(long, env)]
+        epoch: u64,
+    },
+}
@@ -0,0 +1,103 @@
+//! Development tasks of the repository, run with `cargo xtask <task>`.
+//!
+//! `cargo xtask gas-check` executes the optimized pre-phase4 WASM with
+//! `gas-profile` in the runtime of the node, metering its gas like the node
+//! does, and fails if it exceeds the gas budget of the governance execution.
+//! The build runs it once the WASM is optimized, so that a proposal that
+//! cannot execute is caught before anyone locks a deposit.
+//!
+//! `cargo xtask simulate`, `cargo xtask report`, and `cargo xtask verify`
+//! wrap the simulator and the verifier, so that rehearsing the proposal over
+//! an exported chain state and checking its outcome on chain each take a
+//! single command.
+//!
+//! `cargo xtask new-phase --config <phase file>` scaffolds a new phase crate
+//! with the same layout as the others, its token table resolved from the token
+//! registry.
+
+pub mod config;
+pub mod scaffold;
+
+use std::path::Path;
+use std::process::{self, Command, ExitStatus};
+
+use clap::Parser;
+use config::{AppConfig, Task};
+
+/// The maximum gas of a block in the mainnet genesis parameters, which bounds
+/// the gas of the governance proposals executed in it
+pub const MAX_BLOCK_GAS: u64 = 3_000_000;
+
+/// Run the given binary of the given package of the workspace in release mode
+/// with the given arguments
+fn run(package: &str, bin: &str, args: &[String]) -> ExitStatus {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    Command::new(cargo)
+        .args(["run", "--release", "--package", package, "--bin", bin, "--"])
+        .args(args)
+        .status()
+        .unwrap_or_else(|error| panic!("unable to run {bin}: {error}"))
+}
+
+/// Exit with the code of the given status if it is a failure
+fn exit_on_failure(status: ExitStatus) {
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1))
+    }
+}
+
+fn main() {
+    let config = AppConfig::parse();
+
+    match config.task {
+        Task::GasCheck { budget } => {
+            let status = run(
+                "gas-profile",
+                "gas-profile",
+                &["--gas-limit".to_owned(), budget.to_string()],
+            );
+            if !status.success() {
+                println!("The pre-phase4 transaction does not fit into the gas budget of {budget}");
+            }
+            exit_on_failure(status);
+        }
+        Task::Simulate { state, epoch } => {
+            let mut args = vec!["--state".to_owned(), state.display().to_string()];
+            if let Some(epoch) = epoch {
+                args.extend(["--epoch".to_owned(), epoch.to_string()]);
+            }
+            exit_on_failure(run("simulator", "simulate", &args));
+        }
+        Task::Report { state, format } => {
+            let args = [
+                "--state".to_owned(),
+                state.display().to_string(),
+                "--report".to_owned(),
+                format,
+            ];
+            exit_on_failure(run("simulator", "simulate", &args));
+        }
+        Task::NewPhase { config } => {
+            let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+            if let Err(error) = scaffold::new_phase(&workspace, &config) {
+                println!("{error}");
+                process::exit(1)
+            }
+        }
+        Task::Verify {
+            tendermint_url,
+            state,
+            epoch,
+        } => {
+            let args = [
+                "--tendermint-url".to_owned(),
+                tendermint_url,
+                "--state".to_owned(),
+                state.display().to_string(),
+                "--epoch".to_owned(),
+                epoch.to_string(),
+            ];
+            exit_on_failure(run("verify", "verify", &args));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Scaffold a new phase crate from a phase file, so that every phase starts
+//! from the same layout: a `cdylib` crate whose transaction entrypoint
+//! delegates to a generic `apply`, a token table resolved from the token
+//! registry, and a snapshot test of its writes.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The token registry shared by the proposals
+const REGISTRY_FILE: &str = "token-registry.json";
+
+/// The description of a new phase
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseFile {
+    /// The name of the crate, which is also its directory
+    pub name: String,
+    /// The description of the crate
+    pub description: String,
+    /// The symbols, in the token registry, of the tokens of the proposal
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+/// A token of the registry
+#[derive(Deserialize)]
+struct RegistryToken {
+    symbol: String,
+    channel: String,
+    base: String,
+    denomination: u8,
+}
+
+/// Create the crate of the phase described by the given phase file in the
+/// given workspace, and add it to the members of the workspace and to the
+/// sources of the Earthfile
+pub fn new_phase(workspace: &Path, phase_file: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(phase_file)
+        .map_err(|error| format!("unable to read {}: {error}", phase_file.display()))?;
+    let phase: PhaseFile = toml::from_str(&contents)
+        .map_err(|error| format!("unable to parse {}: {error}", phase_file.display()))?;
+    if phase.name.is_empty()
+        || !phase
+            .name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+    {
+        return Err(format!("{:?} is not a valid crate name", phase.name));
+    }
+    let crate_dir = workspace.join(&phase.name);
+    if crate_dir.exists() {
+        return Err(format!("{} already exists", crate_dir.display()));
+    }
+
+    let registry = fs::read_to_string(workspace.join(REGISTRY_FILE))
+        .map_err(|error| format!("unable to read {REGISTRY_FILE}: {error}"))?;
+    let registry: Vec<RegistryToken> = serde_json::from_str(&registry)
+        .map_err(|error| format!("unable to parse {REGISTRY_FILE}: {error}"))?;
+    let tokens = phase
+        .tokens
+        .iter()
+        .map(|symbol| {
+            registry
+                .iter()
+                .find(|token| &token.symbol == symbol)
+                .ok_or_else(|| format!("{symbol} is not in {REGISTRY_FILE}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    fs::create_dir_all(crate_dir.join("src"))
+        .map_err(|error| format!("unable to create {}: {error}", crate_dir.display()))?;
+    write_file(&crate_dir.join("Cargo.toml"), &cargo_manifest(&phase))?;
+    write_file(&crate_dir.join("src/lib.rs"), &lib_source(&tokens))?;
+    add_workspace_member(workspace, &phase.name)?;
+    add_earthfile_source(workspace, &phase.name)
+}
+
+/// Write the given contents to the given file
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents)
+        .map_err(|error| format!("unable to write {}: {error}", path.display()))
+}
+
+/// Render the manifest of the crate of the given phase
+fn cargo_manifest(phase: &PhaseFile) -> String {
+    format!(
+        r#"[package]
+name = "{}"
+description = {:?}
+authors.workspace = true
+edition.workspace = true
+license.workspace = true
+version.workspace = true
+
+# See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+[dependencies]
+namada_tx_prelude.workspace = true
+rlsf.workspace = true
+getrandom.workspace = true
+
+[dev-dependencies]
+test-utils.workspace = true
+
+[lib]
+crate-type = ["cdylib"]
+"#,
+        phase.name, phase.description
+    )
+}
+
+/// Render the source of the crate of a phase with the given tokens
+fn lib_source(tokens: &[&RegistryToken]) -> String {
+    let mut table = String::new();
+    for token in tokens {
+        writeln!(
+            table,
+            "    ({}, {:?}, {:?}),",
+            token.denomination, token.channel, token.base
+        )
+        .unwrap();
+    }
+    format!(
+        r#"use namada_tx_prelude::*;
+
+pub type Denomination = u8;
+pub type ChannelId = &'static str;
+pub type BaseToken = &'static str;
+
+/// The IBC tokens of this proposal, from the token registry
+pub const IBC_TOKENS: [(Denomination, ChannelId, BaseToken); {}] = [
+{table}];
+
+#[transaction]
+fn apply_tx(ctx: &mut Ctx, _tx_data: BatchedTx) -> TxResult {{
+    apply(ctx)
+}}
+
+/// Apply the changes of this proposal to the given storage
+pub fn apply<S: StorageRead + StorageWrite>(ctx: &mut S) -> TxResult {{
+    for (denomination, channel_id, base_token) in IBC_TOKENS {{
+        let ibc_denom = format!("transfer/{{channel_id}}/{{base_token}}");
+        let token_address = ibc::ibc_token(&ibc_denom);
+        // 1. Register the denomination of the token
+        token::write_denom(ctx, &token_address, token::Denomination(denomination))?;
+    }}
+
+    Ok(())
+}}
+
+#[cfg(test)]
+mod tests {{
+    use test_utils::{{assert_snapshot, MockCtx}};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_snapshot() {{
+        let mut ctx = MockCtx::default();
+        apply(&mut ctx).unwrap();
+        assert_snapshot(
+            &ctx.diff(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/apply.snap"),
+        );
+    }}
+}}
+"#,
+        tokens.len()
+    )
+}
+
+/// Add the crate with the given name to the members and the default members
+/// of the workspace
+fn add_workspace_member(workspace: &Path, name: &str) -> Result<(), String> {
+    let manifest_path = workspace.join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path)
+        .map_err(|error| format!("unable to read {}: {error}", manifest_path.display()))?;
+    for list in ["\nmembers = [", "\ndefault-members = ["] {
+        let start = manifest
+            .find(list)
+            .ok_or_else(|| format!("the workspace manifest has no{list}"))?;
+        let end = start
+            + manifest[start..]
+                .find("\n]")
+                .ok_or_else(|| format!("the workspace manifest has an unterminated{list}"))?;
+        manifest.insert_str(end, &format!("\n    \"{name}\","));
+    }
+    write_file(&manifest_path, &manifest)
+}
+
+/// Add the directory of the crate with the given name to the sources copied by
+/// the Earthfile
+fn add_earthfile_source(workspace: &Path, name: &str) -> Result<(), String> {
+    let earthfile_path = workspace.join("Earthfile");
+    let earthfile = fs::read_to_string(&earthfile_path)
+        .map_err(|error| format!("unable to read {}: {error}", earthfile_path.display()))?;
+    let mut lines: Vec<String> = earthfile.lines().map(str::to_owned).collect();
+    let copy_line = lines
+        .iter_mut()
+        .find(|line| {
+            line.trim_start()
+                .starts_with("COPY --keep-ts --dir builder ")
+        })
+        .ok_or_else(|| "the Earthfile copies no crate directories".to_owned())?;
+    let sources = copy_line
+        .strip_suffix(" ./")
+        .ok_or_else(|| "the Earthfile copies the crate directories elsewhere".to_owned())?;
+    *copy_line = format!("{sources} {name} ./");
+    write_file(&earthfile_path, &(lines.join("\n") + "\n"))
+}